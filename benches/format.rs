@@ -0,0 +1,71 @@
+//! Formatting benchmarks, compared with `format!` for the same output.
+//!
+//! Compare a change with `cargo bench -- --save-baseline before` on the old
+//! tree and `cargo bench -- --baseline before` on the new one.
+
+#[macro_use]
+extern crate criterion;
+extern crate strfmt;
+
+use std::collections::HashMap;
+
+use criterion::{black_box, Criterion};
+use strfmt::{strfmt, strfmt_write, Template};
+
+fn metrics() -> HashMap<String, f64> {
+    let mut vars = HashMap::new();
+    vars.insert("count".to_string(), 1234567.0);
+    vars.insert("mean".to_string(), 0.000123);
+    vars.insert("max".to_string(), 98765.4321);
+    vars
+}
+
+fn bench_numbers(c: &mut Criterion) {
+    let vars = metrics();
+    let fmtstr = "count={count:>12.0} mean={mean:<10.3e} max={max:^14.2}";
+    c.bench_function("strfmt numbers", |b| {
+        b.iter(|| strfmt(black_box(fmtstr), &vars).unwrap())
+    });
+    let mut out = String::with_capacity(128);
+    c.bench_function("strfmt_write numbers", |b| {
+        b.iter(|| {
+            out.clear();
+            strfmt_write(black_box(fmtstr), &vars, &mut out).unwrap();
+        })
+    });
+    let template = Template::parse(fmtstr).unwrap();
+    c.bench_function("Template::render_write numbers", |b| {
+        b.iter(|| {
+            out.clear();
+            template.render_write(&vars, &mut out).unwrap();
+        })
+    });
+    c.bench_function("format! numbers (baseline)", |b| {
+        b.iter(|| {
+            format!(
+                "count={:>12.0} mean={:<10.3e} max={:^14.2}",
+                black_box(vars["count"]),
+                black_box(vars["mean"]),
+                black_box(vars["max"])
+            )
+        })
+    });
+}
+
+fn bench_literals(c: &mut Criterion) {
+    let mut vars = HashMap::new();
+    vars.insert("level".to_string(), "INFO");
+    vars.insert("msg".to_string(), "request handled");
+    let fmtstr = "2024-01-01T00:00:00Z [{level:<5}] service=api host=web-01 \
+                  region=eu-west-1 path=/v1/items method=GET {{status}} msg={msg}";
+    c.bench_function("strfmt literals", |b| {
+        b.iter(|| strfmt(black_box(fmtstr), &vars).unwrap())
+    });
+    let long = format!("{} {{msg}}", fmtstr.repeat(20));
+    c.bench_function("strfmt long log line", |b| {
+        b.iter(|| strfmt(black_box(&long), &vars).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_numbers, bench_literals);
+criterion_main!(benches);
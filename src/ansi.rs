@@ -0,0 +1,285 @@
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use types::*;
+use width::{measure, measure_back, WidthMode};
+
+/// the SGR sequence resetting all colors and styles
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// the colors, in the order of their SGR codes
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// the attributes and their SGR codes
+const ATTRIBUTES: [(&str, u8); 6] = [
+    ("bold", 1),
+    ("dim", 2),
+    ("italic", 3),
+    ("underline", 4),
+    ("reverse", 7),
+    ("strikethrough", 9),
+];
+
+/// the SGR style of a field from its `!style=` annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Style {
+    fg: Option<u8>,
+    bg: Option<u8>,
+    /// bit `n` is set for the attribute with code `n`
+    attributes: u16,
+}
+
+impl Style {
+    /// parse a comma separated list of colors and attributes, like
+    /// `red,bold` or `bright_white,on_blue,underline`
+    pub(crate) fn parse(s: &str) -> Result<Style> {
+        let mut style = Style::default();
+        for name in s.split(',') {
+            let (name, background) = match name.strip_prefix("on_") {
+                Some(color) => (color, true),
+                None => (name, false),
+            };
+            let (color, bright) = match name.strip_prefix("bright_") {
+                Some(color) => (color, true),
+                None => (name, false),
+            };
+            if let Some(i) = COLORS.iter().position(|&c| c == color) {
+                let code = i as u8
+                    + match (background, bright) {
+                        (false, false) => 30,
+                        (false, true) => 90,
+                        (true, false) => 40,
+                        (true, true) => 100,
+                    };
+                if background {
+                    style.bg = Some(code);
+                } else {
+                    style.fg = Some(code);
+                }
+                continue;
+            }
+            match ATTRIBUTES.iter().find(|&&(a, _)| a == name && !background) {
+                Some(&(_, code)) => style.attributes |= 1 << code,
+                None => {
+                    let mut msg = String::new();
+                    write!(msg, "Invalid style: {}", s).unwrap();
+                    return Err(FmtError::Invalid(msg));
+                }
+            }
+        }
+        Ok(style)
+    }
+
+    /// write the SGR sequence that starts the style
+    pub(crate) fn write_start(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        let codes = ATTRIBUTES
+            .iter()
+            .map(|&(_, code)| code)
+            .filter(|&code| self.attributes & (1 << code) != 0)
+            .chain(self.fg)
+            .chain(self.bg);
+        out.write_str("\x1b[")?;
+        for (i, code) in codes.enumerate() {
+            if i > 0 {
+                out.write_char(';')?;
+            }
+            write!(out, "{}", code)?;
+        }
+        out.write_char('m')
+    }
+}
+
+impl fmt::Display for Style {
+    /// write the names of the style, which parse back to an equal style
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let attributes = ATTRIBUTES
+            .iter()
+            .filter(|&&(_, code)| self.attributes & (1 << code) != 0)
+            .map(|&(name, _)| ("", "", name));
+        let color = |code: u8| {
+            let (background, bright, i) = match code {
+                30..=37 => ("", "", code - 30),
+                90..=97 => ("", "bright_", code - 90),
+                40..=47 => ("on_", "", code - 40),
+                _ => ("on_", "bright_", code - 100),
+            };
+            (background, bright, COLORS[i as usize])
+        };
+        let names = attributes
+            .chain(self.fg.map(color))
+            .chain(self.bg.map(color));
+        for (i, (background, bright, name)) in names.enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            write!(f, "{}{}{}", background, bright, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// the length in bytes of the escape sequence at the start of `s`, which
+/// starts with ESC. An unterminated sequence runs to the end of `s`.
+fn escape_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    match bytes.get(1) {
+        // CSI: parameter and intermediate bytes, then a final byte
+        Some(&b'[') => {
+            let mut i = 2;
+            while i < bytes.len() && (0x20..=0x3f).contains(&bytes[i]) {
+                i += 1;
+            }
+            if i < bytes.len() && (0x40..=0x7e).contains(&bytes[i]) {
+                i += 1;
+            }
+            i
+        }
+        // OSC: ends with BEL or ESC \
+        Some(&b']') => {
+            let mut i = 2;
+            while i < bytes.len() {
+                match bytes[i] {
+                    0x07 => return i + 1,
+                    0x1b if bytes.get(i + 1) == Some(&b'\\') => return i + 2,
+                    _ => i += 1,
+                }
+            }
+            i
+        }
+        // intermediate bytes, then a final byte
+        Some(_) => {
+            let mut i = 1;
+            while i < bytes.len() && (0x20..=0x2f).contains(&bytes[i]) {
+                i += 1;
+            }
+            if i < bytes.len() && bytes[i].is_ascii() {
+                i += 1;
+            }
+            i
+        }
+        None => 1,
+    }
+}
+
+/// measure `s` like [measure], with escape sequences counting as zero width.
+/// Returns the width, the number of bytes of `s` that fit and if a reset
+/// must be written after them because escape sequences were cut off.
+pub(crate) fn measure_ansi(
+    s: &str,
+    mode: WidthMode,
+    precision: Option<usize>,
+) -> (usize, usize, bool) {
+    let mut width = 0;
+    let mut pos = 0;
+    let mut escaped = false;
+    while pos < s.len() {
+        let text_end = match s[pos..].find('\x1b') {
+            Some(i) => pos + i,
+            None => s.len(),
+        };
+        let text = &s[pos..text_end];
+        let (w, end) = measure(text, mode, precision.map(|p| p - width));
+        width += w;
+        if end < text.len() {
+            return (width, pos + end, escaped);
+        }
+        pos = text_end;
+        if pos < s.len() {
+            pos += escape_len(&s[pos..]);
+            escaped = true;
+        }
+    }
+    (width, s.len(), false)
+}
+
+/// measure `s` from the end like [measure_back](crate::width::measure_back),
+/// with escape sequences counting as zero width. Returns the width and the
+/// byte offset of the part of `s` that fits.
+pub(crate) fn measure_ansi_back(
+    s: &str,
+    mode: WidthMode,
+    precision: Option<usize>,
+) -> (usize, usize) {
+    let total = measure_ansi(s, mode, None).0;
+    let precision = match precision {
+        Some(p) if p < total => p,
+        _ => return (total, 0),
+    };
+    // find the text between escapes where the cut falls
+    let mut after = total;
+    let mut pos = 0;
+    loop {
+        let text_end = match s[pos..].find('\x1b') {
+            Some(i) => pos + i,
+            None => s.len(),
+        };
+        let text = &s[pos..text_end];
+        after -= measure(text, mode, None).0;
+        if after <= precision {
+            let (w, start) = measure_back(text, mode, Some(precision - after));
+            return (after + w, pos + start);
+        }
+        pos = text_end + escape_len(&s[text_end..]);
+    }
+}
+
+/// the escape sequences in `s`, in order
+pub(crate) fn escapes(s: &str) -> impl Iterator<Item = &str> {
+    let mut pos = 0;
+    core::iter::from_fn(move || {
+        let start = pos + s[pos..].find('\x1b')?;
+        pos = start + escape_len(&s[start..]);
+        Some(&s[start..pos])
+    })
+}
+
+#[test]
+fn test_measure_ansi() {
+    let s = "\x1b[31mhello\x1b[0m world";
+    assert_eq!(measure_ansi(s, WidthMode::Chars, Some(3)), (3, 8, true));
+    assert_eq!(measure_ansi_back(s, WidthMode::Chars, Some(3)), (3, 17));
+    assert_eq!(measure_ansi_back(s, WidthMode::Chars, Some(8)), (8, 8));
+    assert_eq!(measure_ansi_back(s, WidthMode::Chars, Some(11)), (11, 0));
+    assert_eq!(
+        measure_ansi_back(s, WidthMode::Chars, Some(0)),
+        (0, s.len())
+    );
+    let found: alloc::vec::Vec<&str> = escapes(&s[3..]).collect();
+    assert_eq!(found.join(""), "\x1b[0m");
+    assert_eq!(escapes(s).count(), 2);
+}
+
+#[test]
+fn test_style() {
+    fn start(s: &str) -> String {
+        let mut out = String::new();
+        Style::parse(s).unwrap().write_start(&mut out).unwrap();
+        out
+    }
+    assert_eq!(start("red"), "\x1b[31m");
+    assert_eq!(start("red,bold"), "\x1b[1;31m");
+    assert_eq!(start("underline,bright_white,on_blue"), "\x1b[4;97;44m");
+    assert_eq!(start("on_bright_black,dim,italic"), "\x1b[2;3;100m");
+    use alloc::string::ToString;
+    let names = "bold,underline,bright_white,on_blue";
+    assert_eq!(Style::parse(names).unwrap().to_string(), names);
+    assert!(Style::parse("").is_err());
+    assert!(Style::parse("red,").is_err());
+    assert!(Style::parse("on_bold").is_err());
+    assert!(Style::parse("purple").is_err());
+}
+
+#[test]
+fn test_escape_len() {
+    assert_eq!(escape_len("\x1b[0mabc"), 4);
+    assert_eq!(escape_len("\x1b[38;5;196mx"), 11);
+    assert_eq!(escape_len("\x1b[1"), 3);
+    assert_eq!(escape_len("\x1b]8;;http://a\x1b\\x"), 15);
+    assert_eq!(escape_len("\x1b]0;title\x07x"), 10);
+    assert_eq!(escape_len("\x1b(Bx"), 3);
+    assert_eq!(escape_len("\x1bMx"), 2);
+    assert_eq!(escape_len("\x1b\u{e9}"), 1);
+    assert_eq!(escape_len("\x1b"), 1);
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashMap};
+use std::string::{String, ToString};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use template::Template;
+use types::*;
+use Vars;
+
+/// A bounded, thread-safe cache of parsed [Template]s keyed by format string.
+///
+/// A format string is parsed the first time it is used, after that the shared
+/// template is returned. When the cache is full the least recently used
+/// template is evicted. Format strings that fail to parse are not cached.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use strfmt::TemplateCache;
+///
+/// let cache = TemplateCache::new(100);
+/// let mut vars = HashMap::new();
+/// vars.insert("name".to_string(), "bob");
+///
+/// assert_eq!(cache.strfmt("hi {name}", &vars).unwrap(), "hi bob");
+/// assert_eq!(cache.strfmt("hi {name}", &vars).unwrap(), "hi bob");
+/// assert_eq!((cache.hits(), cache.misses()), (1, 1));
+/// ```
+pub struct TemplateCache {
+    capacity: usize,
+    inner: RwLock<Inner>,
+    /// incremented on every use, to order the entries by their last use
+    tick: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Inner {
+    entries: HashMap<String, Entry>,
+    /// the entries by the tick they were last ordered at. A hit only stamps
+    /// its entry, so an entry used since is moved when it reaches the front.
+    order: BTreeMap<u64, String>,
+}
+
+struct Entry {
+    template: Arc<Template>,
+    last_used: AtomicU64,
+}
+
+impl Inner {
+    fn evict_lru(&mut self) {
+        while let Some((ordered, fmtstr)) = self.order.pop_first() {
+            let last_used = match self.entries.get_mut(&fmtstr) {
+                Some(entry) => *entry.last_used.get_mut(),
+                None => continue,
+            };
+            if last_used == ordered {
+                self.entries.remove(&fmtstr);
+                return;
+            }
+            self.order.insert(last_used, fmtstr);
+        }
+    }
+}
+
+impl TemplateCache {
+    /// create a cache holding at most `capacity` templates
+    pub fn new(capacity: usize) -> TemplateCache {
+        TemplateCache {
+            capacity,
+            inner: RwLock::new(Inner {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+            }),
+            tick: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// the maximum number of templates held
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// the number of templates held
+    pub fn len(&self) -> usize {
+        self.read().entries.len()
+    }
+
+    /// return true if no templates are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the number of times a template was found in the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// the number of times a format string had to be parsed
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// remove all templates, keeping the counters
+    pub fn clear(&self) {
+        let mut inner = self.write();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    /// return the template for `fmtstr`, parsing it if it is not in the cache
+    ///
+    /// # Errors
+    /// * [FmtError::Invalid] - The format string is structured incorrectly
+    pub fn get(&self, fmtstr: &str) -> Result<Arc<Template>> {
+        if let Some(template) = self.hit(fmtstr) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(template);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // parse without holding the lock so other threads are not blocked
+        let template = Arc::new(Template::parse(fmtstr)?);
+        if self.capacity == 0 {
+            return Ok(template);
+        }
+
+        let mut inner = self.write();
+        // another thread may have added it in the meantime
+        if let Some(entry) = inner.entries.get(fmtstr) {
+            entry
+                .last_used
+                .fetch_max(self.next_tick(), Ordering::Relaxed);
+            return Ok(entry.template.clone());
+        }
+        if inner.entries.len() >= self.capacity {
+            inner.evict_lru();
+        }
+        let tick = self.next_tick();
+        inner.order.insert(tick, fmtstr.to_string());
+        inner.entries.insert(
+            fmtstr.to_string(),
+            Entry {
+                template: template.clone(),
+                last_used: AtomicU64::new(tick),
+            },
+        );
+        Ok(template)
+    }
+
+    /// format `fmtstr` like [strfmt](crate::strfmt()), using the cached template
+    pub fn strfmt<V: Vars + ?Sized>(&self, fmtstr: &str, vars: &V) -> Result<String> {
+        self.get(fmtstr)?.render(vars)
+    }
+
+    /// stamp and return the template of `fmtstr`. Hits share the lock.
+    fn hit(&self, fmtstr: &str) -> Option<Arc<Template>> {
+        let inner = self.read();
+        let entry = inner.entries.get(fmtstr)?;
+        entry
+            .last_used
+            .fetch_max(self.next_tick(), Ordering::Relaxed);
+        Some(entry.template.clone())
+    }
+
+    fn next_tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // the entries are always left consistent, so a panic in another thread
+    // does not make them unusable
+    fn read(&self) -> RwLockReadGuard<'_, Inner> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Inner> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -1,62 +1,620 @@
+use alloc::string::String;
+use core::cmp;
+use core::fmt;
+use core::fmt::Write;
+use core::str;
+
+use fmtstr::write_char;
+use formatter::Formatter;
+use syntax::Syntax;
+use types::*;
+
+/// big enough for any integer in binary and most floats
+const NUM_BUF_LEN: usize = 128;
+
+/// a buffer on the stack to format numbers into without allocating. A
+/// growable buffer moves to the heap instead of failing when it is full.
+pub(crate) struct NumBuf {
+    buf: [u8; NUM_BUF_LEN],
+    len: usize,
+    grow: bool,
+    heap: Option<String>,
+}
+
+impl NumBuf {
+    fn new() -> NumBuf {
+        NumBuf {
+            buf: [0; NUM_BUF_LEN],
+            len: 0,
+            grow: false,
+            heap: None,
+        }
+    }
+
+    /// a buffer for the rare number that is longer than `NUM_BUF_LEN`
+    pub(crate) fn growable() -> NumBuf {
+        NumBuf {
+            grow: true,
+            ..NumBuf::new()
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        if let Some(ref heap) = self.heap {
+            return heap;
+        }
+        // only whole `str`s are ever written into the buffer
+        str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+        if let Some(ref mut heap) = self.heap {
+            heap.clear();
+        }
+    }
+
+    /// append to a growable buffer, which can't fail
+    fn push_str(&mut self, s: &str) {
+        debug_assert!(self.grow);
+        self.write_str(s).unwrap();
+    }
+
+    fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+}
+
+impl fmt::Write for NumBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(ref mut heap) = self.heap {
+            heap.push_str(s);
+            return Ok(());
+        }
+        let end = self.len + s.len();
+        if end > NUM_BUF_LEN {
+            if !self.grow {
+                return Err(fmt::Error);
+            }
+            let mut heap = String::with_capacity(end * 2);
+            heap.push_str(self.as_str());
+            heap.push_str(s);
+            self.heap = Some(heap);
+            return Ok(());
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// how the digits of a number are written, for the number formats of other
+/// syntaxes that a spec can't express
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct NumberStyle {
+    /// written after the sign, i.e. a currency symbol
+    pub(crate) prefix: &'static str,
+    /// the integer part is padded with zeros to this many digits
+    pub(crate) min_digits: usize,
+    /// the number is padded with zeros after its sign and prefix to the
+    /// width, like the `0` flag of rust
+    pub(crate) zero_pad: bool,
+    /// the exponent is written with a sign and at least this many digits,
+    /// like .NET (`E+003`). Zero writes it like rust.
+    pub(crate) exp_digits: usize,
+    /// a float without a type or precision is written in the shortest form
+    /// like .NET's `G`, switching to this exponent letter for an exponent
+    /// below -4 or from 15
+    pub(crate) shortest_exp: Option<char>,
+}
+
+/// counts the chars written to it
+struct CharCount(usize);
+
+impl fmt::Write for CharCount {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+impl<'a, 'b> Formatter<'a, 'b> {
+    /// write `args` using the width and alignment without allocating unless
+    /// the number is longer than `NUM_BUF_LEN`, used by the number formatters
+    pub(crate) fn write_padded(&mut self, args: fmt::Arguments) -> Result<()> {
+        let style = self.number_style();
+        let after_sign = self.options().python() || self.bits().is_some();
+        if after_sign && self.align() == Alignment::Equal {
+            let mut s = NumBuf::growable();
+            s.write_fmt(args)?;
+            let mut padded = NumBuf::growable();
+            self.pad_after_sign(s.as_str(), style, &mut padded);
+            return self.str_unchecked(padded.as_str());
+        }
+        if self.grouping().is_some() || style != NumberStyle::default() {
+            let mut s = NumBuf::growable();
+            s.write_fmt(args)?;
+            let mut grouped = NumBuf::growable();
+            self.group_digits(s.as_str(), style, &mut grouped);
+            return self.str_unchecked(grouped.as_str());
+        }
+        let mut buf = NumBuf::new();
+        if buf.write_fmt(args).is_ok() {
+            return self.str_unchecked(buf.as_str());
+        }
+        // too long for the buffer, count it before writing it directly
+        let mut count = CharCount(0);
+        count.write_fmt(args)?;
+        let fill = self.fill();
+        let pad = self.pad_before(count.0)?;
+        self.write_fmt(args)?;
+        write_char(self, fill, pad)?;
+        Ok(())
+    }
+
+    /// the length in bytes of the sign and base prefix of the number `s`
+    fn sign_len(&self, s: &str) -> usize {
+        let sign = if s.starts_with(['-', '+', ' ']) { 1 } else { 0 };
+        let based = matches!(self.ty(), Some('b') | Some('o') | Some('x') | Some('X'));
+        // the prefix is missing if it was written before the padding
+        let prefixed = ["0b", "0o", "0x"].iter().any(|p| s[sign..].starts_with(p));
+        if self.alternate() && based && prefixed {
+            sign + 2
+        } else {
+            sign
+        }
+    }
+
+    /// pad the number `s` to the width with the fill after its sign and
+    /// base prefix, like the `=` alignment of python. Zeros are grouped
+    /// like digits, unless `s` is `inf` or `nan`.
+    fn pad_after_sign(&self, s: &str, style: NumberStyle, out: &mut NumBuf) {
+        let width = self.width().unwrap_or(0);
+        let fill = self.fill();
+        // no digit or prefix of a finite number has an `n`
+        let finite = !s.contains(['n', 'N']);
+        if fill == '0' && finite {
+            let mut style = style;
+            loop {
+                out.clear();
+                self.group_digits(s, style, out);
+                if out.as_str().chars().count() >= width {
+                    return;
+                }
+                style.min_digits += 1;
+            }
+        }
+        let mut grouped = NumBuf::growable();
+        self.group_digits(s, style, &mut grouped);
+        let grouped = grouped.as_str();
+        let (head, tail) = grouped.split_at(self.sign_len(s));
+        out.push_str(head);
+        for _ in grouped.chars().count()..width {
+            out.push(fill);
+        }
+        out.push_str(tail);
+    }
+
+    /// insert the grouping separator into the integer part of the number `s`
+    /// and apply `style`
+    fn group_digits(&self, s: &str, style: NumberStyle, out: &mut NumBuf) {
+        let mut widened = NumBuf::growable();
+        let s = match (style.exp_digits, s.find(['e', 'E'])) {
+            (0, _) | (_, None) => s,
+            (digits, Some(e)) => {
+                widen_exponent(s, e, digits, &mut widened);
+                widened.as_str()
+            }
+        };
+        let (sign, rest) = s.split_at(if s.starts_with(['-', '+', ' ']) { 1 } else { 0 });
+        let ty = self.ty();
+        let (base, rest) = rest.split_at(self.sign_len(s) - sign.len());
+        let hex = matches!(ty, Some('x') | Some('X'));
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || hex && c.is_ascii_hexdigit()))
+            .unwrap_or(rest.len());
+        let (digits, tail) = rest.split_at(end);
+        // PEP 515: binary, octal and hex digits are grouped by four
+        let every = match ty {
+            Some('b') | Some('o') | Some('x') | Some('X') => 4,
+            _ => 3,
+        };
+        let mut zeros = style.min_digits.saturating_sub(digits.len());
+        if let (true, Some(width)) = (style.zero_pad, self.width()) {
+            let len = s.chars().count() + style.prefix.chars().count() + zeros;
+            zeros += width.saturating_sub(len);
+        }
+        let count = zeros + digits.len();
+        out.push_str(sign);
+        out.push_str(base);
+        out.push_str(style.prefix);
+        let all = core::iter::repeat('0').take(zeros).chain(digits.chars());
+        for (i, c) in all.enumerate() {
+            if let Some(sep) = self.grouping() {
+                if i > 0 && (count - i) % every == 0 {
+                    out.push(sep);
+                }
+            }
+            out.push(c);
+        }
+        out.push_str(tail);
+    }
+
+    /// write `x` in the general format (`g`): like `e` if the exponent is
+    /// below -4 or at least `precision` significant digits, else like `f`,
+    /// without trailing zeros. `plus` is written before a positive number.
+    pub(crate) fn write_general(
+        &mut self,
+        x: f64,
+        precision: usize,
+        upper: bool,
+        plus: &str,
+    ) -> Result<()> {
+        if !x.is_finite() {
+            return self.write_padded(format_args!("{}{}", plus, x));
+        }
+        let p = cmp::max(precision, 1);
+        let mut sci = NumBuf::growable();
+        write!(sci, "{:.*e}", p - 1, x)?;
+        let (mantissa, exp) = split_exponent(sci.as_str());
+        if exp < -4 || exp >= p as i32 {
+            let e = if upper { 'E' } else { 'e' };
+            self.write_padded(format_args!("{}{}{}{}", plus, trim_zeros(mantissa), e, exp))
+        } else {
+            let mut fixed = NumBuf::growable();
+            write!(fixed, "{:.*}", (p as i32 - 1 - exp) as usize, x)?;
+            self.write_padded(format_args!("{}{}", plus, trim_zeros(fixed.as_str())))
+        }
+    }
+}
+
+impl<'a, 'b> Formatter<'a, 'b> {
+    /// the sign written before a number that is not negative
+    pub(crate) fn plus(&self) -> &'static str {
+        match self.sign() {
+            Sign::Plus => "+",
+            Sign::Space if self.options().python() => " ",
+            _ => "",
+        }
+    }
+
+    /// return true if the `+` sign and the base prefix are written before
+    /// the padding instead of with the number, like the braces syntax always
+    /// did. Rust, printf and python mode and a bit width pad them with the
+    /// number.
+    pub(crate) fn sign_before_padding(&self) -> bool {
+        !self.options().python()
+            && self.bits().is_none()
+            && !matches!(self.syntax(), Syntax::Rust | Syntax::Percent)
+    }
+
+    /// return true if the magnitude `ax` of a negative float is written as
+    /// zero, so the `z` option drops its sign
+    pub(crate) fn rounds_to_zero(&self, ax: f64, ty: char) -> bool {
+        let default = if self.options().python() {
+            Some(6)
+        } else {
+            None
+        };
+        let (ax, precision) = match ty {
+            'f' | 'F' => (ax, self.precision().or(default)),
+            '%' => (ax * 100.0, self.precision().or(Some(6))),
+            // the other types always keep a nonzero digit
+            _ => (ax, None),
+        };
+        match precision {
+            // only a number below one can round to zero
+            Some(_) if ax >= 1.0 => false,
+            Some(p) => {
+                let mut s = NumBuf::growable();
+                write!(s, "{:.*}", p, ax).unwrap();
+                s.as_str().bytes().all(|b| b == b'0' || b == b'.')
+            }
+            None => ax == 0.0,
+        }
+    }
+
+    /// write the float `x` like python's `str.format`. `shortest` is the
+    /// magnitude of `x` in the shortest `{:e}` form of its own type.
+    pub(crate) fn write_python(&mut self, x: f64, shortest: &str) -> Result<()> {
+        let ty = self.ty();
+        let upper = matches!(ty, Some('E') | Some('F') | Some('G'));
+        let alternate = self.alternate();
+        let negative = x.is_sign_negative() && !x.is_nan();
+        let sign = if negative { "-" } else { self.plus() };
+        let ax = if negative { -x } else { x };
+        let mut body = NumBuf::growable();
+        if !ax.is_finite() {
+            body.push_str(match (ax.is_nan(), upper) {
+                (true, false) => "nan",
+                (true, true) => "NAN",
+                (false, false) => "inf",
+                (false, true) => "INF",
+            });
+            if ty == Some('%') {
+                body.push('%');
+            }
+        } else {
+            match (ty, self.precision()) {
+                (Some('f'), p) | (Some('F'), p) => {
+                    python_fixed(&mut body, ax, p.unwrap_or(6), alternate)
+                }
+                (Some('%'), p) => {
+                    python_fixed(&mut body, ax * 100.0, p.unwrap_or(6), alternate);
+                    body.push('%');
+                }
+                (Some('e'), p) | (Some('E'), p) => {
+                    let p = p.unwrap_or(6);
+                    let mut sci = NumBuf::growable();
+                    write!(sci, "{:.*e}", p, ax)?;
+                    let (mantissa, exp) = split_exponent(sci.as_str());
+                    body.push_str(mantissa);
+                    if alternate && p == 0 {
+                        body.push('.');
+                    }
+                    python_exponent(&mut body, exp, upper);
+                }
+                (Some('g'), p) | (Some('n'), p) | (Some('G'), p) => {
+                    python_general(&mut body, ax, p.unwrap_or(6), alternate, upper, false)
+                }
+                // like `g`, with a digit after the point and an exponent sooner
+                (_, Some(p)) => python_general(&mut body, ax, p, alternate, false, true),
+                (_, None) => python_repr(&mut body, shortest),
+            }
+        }
+        let prev_prec = self.precision();
+        self.set_precision(None);
+        let out = self.write_padded(format_args!("{}{}", sign, body.as_str()));
+        self.set_precision(prev_prec);
+        out
+    }
+}
+
+/// write the exponent of `s`, which starts at byte `e`, with a sign and at
+/// least `digits` digits
+fn widen_exponent(s: &str, e: usize, digits: usize, out: &mut NumBuf) {
+    let end = s[e + 1..]
+        .find(|c: char| !(c.is_ascii_digit() || c == '-' || c == '+'))
+        .map_or(s.len(), |i| e + 1 + i);
+    let exp: i32 = s[e + 1..end].parse().unwrap_or(0);
+    out.push_str(&s[..=e]);
+    out.push(if exp < 0 { '-' } else { '+' });
+    write!(out, "{:01$}", exp.unsigned_abs(), digits).unwrap();
+    out.push_str(&s[end..]);
+}
+
+/// split the `{:e}` form of a float into its mantissa and exponent
+fn split_exponent(sci: &str) -> (&str, i32) {
+    let e = sci.find('e').unwrap();
+    (&sci[..e], sci[e + 1..].parse().unwrap())
+}
+
+/// write a python exponent, with a sign and at least two digits
+fn python_exponent(out: &mut NumBuf, exp: i32, upper: bool) {
+    out.push(if upper { 'E' } else { 'e' });
+    write!(out, "{:+03}", exp).unwrap();
+}
+
+/// write `ax` with `precision` digits after the point, `alternate` keeps the point
+fn python_fixed(out: &mut NumBuf, ax: f64, precision: usize, alternate: bool) {
+    write!(out, "{:.*}", precision, ax).unwrap();
+    if alternate && precision == 0 {
+        out.push('.');
+    }
+}
+
+/// write `ax` in python's general format with `precision` significant
+/// digits. `alternate` keeps the trailing zeros, `add_dot_0` is for a
+/// float without a type: it switches to the exponent one digit sooner and
+/// keeps a digit after the point.
+fn python_general(
+    out: &mut NumBuf,
+    ax: f64,
+    precision: usize,
+    alternate: bool,
+    upper: bool,
+    add_dot_0: bool,
+) {
+    let p = cmp::max(precision, 1);
+    let mut sci = NumBuf::growable();
+    write!(sci, "{:.*e}", p - 1, ax).unwrap();
+    let (mantissa, exp) = split_exponent(sci.as_str());
+    let limit = if add_dot_0 { p as i32 - 1 } else { p as i32 };
+    if exp < -4 || exp >= limit {
+        out.push_str(if alternate {
+            mantissa
+        } else {
+            trim_zeros(mantissa)
+        });
+        if alternate && !mantissa.contains('.') {
+            out.push('.');
+        }
+        python_exponent(out, exp, upper);
+    } else {
+        let mut fixed = NumBuf::growable();
+        write!(fixed, "{:.*}", (p as i32 - 1 - exp) as usize, ax).unwrap();
+        let fixed = if alternate {
+            fixed.as_str()
+        } else {
+            trim_zeros(fixed.as_str())
+        };
+        out.push_str(fixed);
+        if add_dot_0 && !fixed.contains('.') {
+            out.push_str(".0");
+        } else if alternate && !fixed.contains('.') {
+            out.push('.');
+        }
+    }
+}
+
+/// write the shortest digits of a float like python's `repr`, from the
+/// shortest `{:e}` form
+fn python_repr(out: &mut NumBuf, shortest: &str) {
+    let (mantissa, exp) = split_exponent(shortest);
+    if !(-4..16).contains(&exp) {
+        out.push_str(mantissa);
+        python_exponent(out, exp, false);
+        return;
+    }
+    let mut digits = NumBuf::growable();
+    for c in mantissa.chars().filter(|&c| c != '.') {
+        digits.push(c);
+    }
+    let digits = digits.as_str();
+    if exp < 0 {
+        out.push_str("0.");
+        for _ in 0..-exp - 1 {
+            out.push('0');
+        }
+        out.push_str(digits);
+        return;
+    }
+    let point = exp as usize + 1;
+    if digits.len() > point {
+        out.push_str(&digits[..point]);
+        out.push('.');
+        out.push_str(&digits[point..]);
+    } else {
+        out.push_str(digits);
+        for _ in digits.len()..point {
+            out.push('0');
+        }
+        out.push_str(".0");
+    }
+}
+
+/// remove the trailing zeros after the decimal point
+fn trim_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
 macro_rules! fmtint {
     ($($t:ident)*) => ($(
         #[allow(unused_comparisons)]
         impl<'a, 'b> Formatter<'a, 'b> {
             pub fn $t(&mut self, x: $t) -> Result<()> {
+                self.check_spec()?;
                 self.set_default_align(Alignment::Right);
 
+                // debug (`?`), `d` and `n` are the same as display for integers
                 let ty = match self.ty() {
-                    None => ' ',
+                    None | Some('?') | Some('d') | Some('n') => ' ',
                     Some(c) => c,
                 };
 
+                // like C, python and .NET, the float types format the value as
+                // a float, rust only has the exponent types for integers
+                let as_float = match self.syntax() {
+                    Syntax::Rust => matches!(ty, 'e' | 'E'),
+                    Syntax::DotNet => true,
+                    _ => self.c_ints(),
+                };
+                if as_float && matches!(ty, 'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%') {
+                    return self.f64(x as f64);
+                }
+
+                if self.positive_zero() {
+                    return Err(FmtError::Invalid("Negative zero coercion (z) not allowed in integer format specifier".to_string()));
+                }
+
                 if !self.is_int_type() {
                     let mut msg = String::new();
                     write!(msg, "Unknown format code {:?} for type", ty).unwrap();
                     return Err(FmtError::TypeError(msg));
                 }
 
+                // like `format!`, rust ignores the precision of integers
+                if self.syntax() == Syntax::Rust {
+                    self.set_precision(None);
+                }
                 if self.precision() != None {
                     return Err(FmtError::TypeError("precision not allowed for integers".to_string()));
                 }
 
-                if self.thousands() {
-                    return Err(FmtError::Invalid("thousands specifier not yet supported".to_string()));
+                if self.wrap().is_some() {
+                    return Err(FmtError::TypeError("Wrapping not allowed for integers".to_string()));
                 }
 
-                if self.fill() == '0' && self.align() == Alignment::Right {
-                    return Err(FmtError::Invalid("sign aware 0 padding not yet supported".to_string()));
+                if ty == 'c' {
+                    if self.sign() != Sign::Unspecified {
+                        return Err(FmtError::Invalid("Sign not allowed with integer format specifier 'c'".to_string()));
+                    }
+                    if self.alternate() {
+                        return Err(FmtError::Invalid("Alternate form (#) not allowed with integer format specifier 'c'".to_string()));
+                    }
+                    let c = if x >= 0 && x as u64 <= 0x10ffff {
+                        char::from_u32(x as u32)
+                    } else {
+                        None
+                    };
+                    return match c {
+                        Some(c) => self.write_padded(format_args!("{}", c)),
+                        None => Err(FmtError::Invalid("%c arg not in range(0x110000)".to_string())),
+                    };
                 }
 
-                let mut s = String::new();
+                let plus = if x >= 0 { self.plus() } else { "" };
 
-                if x >= 0 && self.sign_plus() {
-                    self.write_str("+").unwrap();
+                let prefix = match (self.alternate(), ty) {
+                    (false, _) => "",
+                    // rust ignores `#` for debug
+                    (true, _) if self.ty() == Some('?') => "",
+                    (true, 'b') => "0b",
+                    (true, 'o') => "0o",
+                    (true, 'x') | (true, 'X') => "0x",
+                    _ => {
+                        let mut msg = String::new();
+                        write!(msg, "alternate ('#') cannot be used with type {:?}", ty).unwrap();
+                        return Err(FmtError::Invalid(msg));
+                    }
+                };
+
+                let (plus, prefix) = if self.sign_before_padding() {
+                    self.write_str(plus)?;
+                    self.write_str(prefix)?;
+                    ("", "")
+                } else {
+                    (plus, prefix)
+                };
+
+                if ty == ' ' {
+                    return self.write_padded(format_args!("{}{}", plus, x));
                 }
 
-                if self.alternate() {
-                    match ty {
-                        'b' => self.write_str("0b").unwrap(),
-                        'o' => self.write_str("0o").unwrap(),
-                        'x' | 'X' => self.write_str("0x").unwrap(),
-                        _ => {
+                // in a base a negative number is written in two's complement
+                // of the bit width or of its type like rust, or as `-` and
+                // the magnitude in python mode
+                let v = x as i128;
+                let bits = match self.bits() {
+                    Some(bits) => {
+                        let fits = bits == 128 || (v >= -(1i128 << (bits - 1)) && v < (1i128 << bits));
+                        if !fits {
                             let mut msg = String::new();
-                            write!(msg, "alternate ('#') cannot be used with type {:?}", ty).unwrap();
+                            write!(msg, "{} does not fit in {} bits", x, bits).unwrap();
                             return Err(FmtError::Invalid(msg));
                         }
+                        bits
                     }
-                }
+                    None => ::core::mem::size_of::<$t>() as u32 * 8,
+                };
+                let (minus, v) = if x < 0 && self.bits().is_none() && self.options().python() {
+                    ("-", v.unsigned_abs())
+                } else {
+                    let mask = if bits == 128 { u128::MAX } else { (1u128 << bits) - 1 };
+                    ("", v as u128 & mask)
+                };
 
                 match ty {
-                    ' ' => write!(s, "{}", x).unwrap(),
-                    'b' => write!(s, "{:b}", x).unwrap(),
-                    'o' => write!(s, "{:o}", x).unwrap(),
-                    'x' => write!(s, "{:x}", x).unwrap(),
-                    'X' => write!(s, "{:X}", x).unwrap(),
+                    'b' => self.write_padded(format_args!("{}{}{}{:b}", plus, minus, prefix, v)),
+                    'o' => self.write_padded(format_args!("{}{}{}{:o}", plus, minus, prefix, v)),
+                    'x' => self.write_padded(format_args!("{}{}{}{:x}", plus, minus, prefix, v)),
+                    'X' => self.write_padded(format_args!("{}{}{}{:X}", plus, minus, prefix, v)),
                     _ => unreachable!(),
                 }
-
-                self.str_unchecked(s.as_str())
             }
     })*)
 }
@@ -65,10 +623,13 @@ macro_rules! fmtfloat {
     ($($t:ident)*) => ($(
         impl<'a, 'b> Formatter<'a, 'b> {
             pub fn $t(&mut self, x: $t) -> Result<()> {
+                self.check_spec()?;
                 self.set_default_align(Alignment::Right);
 
+                // like python, `n` is the general format without a locale
                 let ty = match self.ty() {
                     None => 'f',
+                    Some('n') => 'g',
                     Some(c) => c,
                 };
 
@@ -78,46 +639,84 @@ macro_rules! fmtfloat {
                     return Err(FmtError::TypeError(msg));
                 }
 
-                if self.alternate() {
+                let python = self.options().python();
+                if self.alternate() && ty != '?' && !python {
                     return Err(FmtError::TypeError("Alternate form (#) not allowed for floats".to_string()));
                 }
 
-                if self.thousands() {
-                    return Err(FmtError::Invalid("thousands specifier not yet supported".to_string()));
+                if self.ellipsis().is_some() {
+                    return Err(FmtError::TypeError("Truncation marker not allowed for floats".to_string()));
                 }
 
-                if self.fill() == '0' && self.align() == Alignment::Right {
-                    return Err(FmtError::Invalid("sign aware 0 padding not yet supported".to_string()));
+                if self.wrap().is_some() {
+                    return Err(FmtError::TypeError("Wrapping not allowed for floats".to_string()));
                 }
 
-                let mut s = String::new();
+                let negative = x.is_sign_negative();
+                let ax = if negative { -x } else { x };
+                // `z` writes a negative float that rounds to zero as a positive zero
+                let x = if negative && self.positive_zero() && self.rounds_to_zero(f64::from(ax), ty) {
+                    ax
+                } else {
+                    x
+                };
 
-                if x >= (0 as $t) && self.sign_plus() {
-                    self.write_str("+").unwrap();
+                if python {
+                    let mut shortest = NumBuf::growable();
+                    write!(shortest, "{:e}", ax)?;
+                    return self.write_python(f64::from(x), shortest.as_str());
                 }
 
-                match self.precision() {
+                // `-0.0` already has its sign
+                let plus = if x >= (0 as $t) && x.is_sign_positive() { self.plus() } else { "" };
+                let plus = if self.sign_before_padding() {
+                    self.write_str(plus)?;
+                    ""
+                } else {
+                    plus
+                };
+
+                let prev_prec = self.precision();
+                self.set_precision(None);
+                let shortest_exp = self.number_style().shortest_exp;
+                let out = match prev_prec {
+                    None if self.ty().is_none() && shortest_exp.is_some() => {
+                        let mut sci = NumBuf::growable();
+                        write!(sci, "{:e}", x)?;
+                        let sci = sci.as_str();
+                        let e = sci.find('e').unwrap_or(sci.len());
+                        let exp: i32 = sci[e..].get(1..).and_then(|exp| exp.parse().ok()).unwrap_or(0);
+                        if x.is_finite() && !(-4..15).contains(&exp) {
+                            let letter = shortest_exp.unwrap();
+                            self.write_padded(format_args!("{}{}{}{}", plus, &sci[..e], letter, exp))
+                        } else {
+                            self.write_padded(format_args!("{}{}", plus, x))
+                        }
+                    }
                     None => {
                         match ty {
-                            'f' => write!(s, "{}", x).unwrap(),
-                            'e' => write!(s, "{:e}", x).unwrap(),
-                            'E' => write!(s, "{:E}", x).unwrap(),
+                            'f' | 'F' => self.write_padded(format_args!("{}{}", plus, x)),
+                            '?' => self.write_padded(format_args!("{}{:?}", plus, x)),
+                            'e' => self.write_padded(format_args!("{}{:e}", plus, x)),
+                            'E' => self.write_padded(format_args!("{}{:E}", plus, x)),
+                            'g' => self.write_general(f64::from(x), 6, false, plus),
+                            'G' => self.write_general(f64::from(x), 6, true, plus),
+                            '%' => self.write_padded(format_args!("{}{:.6}%", plus, x * 100.0)),
                             _ => unreachable!(),
                         }
                     }
                     Some(p) => {
                         match ty {
-                            'f' => write!(s, "{:.*}", p, x).unwrap(),
-                            'e' => write!(s, "{:.*e}", p, x).unwrap(),
-                            'E' => write!(s, "{:.*E}", p, x).unwrap(),
+                            'f' | 'F' | '?' => self.write_padded(format_args!("{}{:.*}", plus, p, x)),
+                            'e' => self.write_padded(format_args!("{}{:.*e}", plus, p, x)),
+                            'E' => self.write_padded(format_args!("{}{:.*E}", plus, p, x)),
+                            'g' => self.write_general(f64::from(x), p, false, plus),
+                            'G' => self.write_general(f64::from(x), p, true, plus),
+                            '%' => self.write_padded(format_args!("{}{:.*}%", plus, p, x * 100.0)),
                             _ => unreachable!(),
                         }
                     }
-                }
-
-                let prev_prec = self.precision();
-                self.set_precision(None);
-                let out = self.str_unchecked(s.as_str());
+                };
                 self.set_precision(prev_prec);
                 out
             }
@@ -1,78 +1,87 @@
-use std::fmt::Write;
-use std::string::String;
+use alloc::string::{String, ToString};
+use core::cell::Cell;
+use core::cmp;
+use core::fmt;
+use core::fmt::Write;
+use core::str;
 
+use ansi;
+use ansi::{measure_ansi, measure_ansi_back};
 use formatter::Formatter;
+use indent::{split_annotations, Line};
+use options::Options;
+use syntax::{Positions, Syntax};
 use types::*;
+use width::{measure, measure_back};
 
-fn write_char(f: &mut Formatter, c: char, n: usize) {
-    for _ in 0..n {
-        f.write_char(c).unwrap();
-    }
-}
+/// the number of fill chars written at once by [write_char]
+const FILL_RUN: usize = 16;
 
-#[test]
-fn test_write_char() {
-    let mut s = String::new();
-    s.write_str("h ").unwrap();
-    {
-        let mut f = Formatter::from_str("{}", &mut s).unwrap();
-        write_char(&mut f, 'f', 3);
-    }
-    assert!(s == "h fff");
-}
-
-fn write_from<I>(fmt: &mut Formatter, f: I, n: usize) -> usize
-where
-    I: Iterator<Item = char>,
-{
-    // eexaust f or run out of n, return chars written
+/// write `c` to `f` `n` times, in runs instead of one char at a time
+pub(crate) fn write_char(f: &mut Formatter, c: char, n: usize) -> fmt::Result {
     if n == 0 {
-        return 0;
+        return Ok(());
     }
-    let mut n_written: usize = 0;
-    for c in f {
-        fmt.write_char(c).unwrap();
-        n_written += 1;
-        if n_written == n {
-            return n_written;
-        }
+    let mut run = [0u8; FILL_RUN * 4];
+    let char_len = c.encode_utf8(&mut run).len();
+    for i in 1..FILL_RUN {
+        run.copy_within(0..char_len, i * char_len);
     }
-    n_written
+    // only whole chars were copied into the run
+    let run = str::from_utf8(&run[..FILL_RUN * char_len]).unwrap();
+    let mut n = n;
+    while n > 0 {
+        let count = cmp::min(n, FILL_RUN);
+        f.write_str(&run[..count * char_len])?;
+        n -= count;
+    }
+    Ok(())
 }
 
 #[test]
-fn test_write_from() {
+fn test_write_char() {
     let mut s = String::new();
     s.write_str("h ").unwrap();
     {
         let mut f = Formatter::from_str("{}", &mut s).unwrap();
-        write_from(&mut f, "fff".chars(), 5);
+        write_char(&mut f, 'f', 3).unwrap();
     }
     assert!(s == "h fff");
     {
         let mut f = Formatter::from_str("{}", &mut s).unwrap();
-        write_from(&mut f, "xxxx".chars(), 2);
+        write_char(&mut f, '\u{e9}', 20).unwrap();
     }
-    assert!(s == "h fffxx");
+    assert_eq!(s, "h fff".to_string() + &"\u{e9}".repeat(20));
+}
+
+#[test]
+fn test_str_unchecked() {
+    let mut s = String::new();
     {
-        let mut f = Formatter::from_str("{}", &mut s).unwrap();
-        write_from(&mut f, "333".chars(), 3);
+        let mut f = Formatter::from_str("x:*^7.3", &mut s).unwrap();
+        f.str_unchecked("h\u{e9}llo").unwrap();
     }
-    assert!(s == "h fffxx333");
+    assert_eq!(s, "**h\u{e9}l**");
     s.clear();
     {
-        let mut f = Formatter::from_str("{}", &mut s).unwrap();
-        write!(f, "hello").unwrap();
+        let mut f = Formatter::from_str("x:>4.9", &mut s).unwrap();
+        f.str_unchecked("ab").unwrap();
     }
-    assert!(s == "hello");
+    assert_eq!(s, "  ab");
 }
 
 /// implement formatting of strings
 impl<'a, 'b> Formatter<'a, 'b> {
     /// format the given string onto the buffer
     pub fn str(&mut self, s: &str) -> Result<()> {
+        self.check_spec()?;
         self.set_default_align(Alignment::Left);
-        if !(self.ty() == None || self.ty() == Some('s')) {
+        if self.ty() == Some('?') && self.syntax() == Syntax::Rust {
+            // like rust, the quoted and escaped string ignores the rest of the spec
+            write!(self, "{:?}", s)?;
+            return Ok(());
+        }
+        if !(self.ty().is_none() || self.ty() == Some('s')) {
             let mut msg = String::new();
             write!(
                 msg,
@@ -91,6 +100,10 @@ impl<'a, 'b> Formatter<'a, 'b> {
             return Err(FmtError::TypeError(
                 "Cannot specify ',' with 's'".to_string(),
             ));
+        } else if self.positive_zero() {
+            return Err(FmtError::TypeError(
+                "Negative zero coercion (z) not allowed in string format specifier".to_string(),
+            ));
         } else if self.sign().is_unspecified() {
             return Err(FmtError::TypeError(
                 "Sign not allowed in string format specifier".to_string(),
@@ -109,23 +122,169 @@ impl<'a, 'b> Formatter<'a, 'b> {
     /// This can also be used by the `u64` etc methods to finish their formatting while
     /// still using the str formatter for width and alignment
     pub fn str_unchecked(&mut self, s: &str) -> Result<()> {
+        if let (Some(width), Some(indent)) = (self.width(), self.wrap()) {
+            return self.str_wrapped(s, width, indent);
+        }
+        if let (Some(precision), Some((cut, marker))) = (self.precision(), self.ellipsis()) {
+            let width = if self.options().ansi() {
+                measure_ansi(s, self.width_mode(), None).0
+            } else {
+                measure(s, self.width_mode(), None).0
+            };
+            if width > precision {
+                return self.str_ellipsis(s, precision, cut, marker);
+            }
+        }
         let fill = self.fill();
-        let width = self.width();
-        let precision = self.precision();
-        let chars_count = s.chars().count();
-        // precision will limit length
-        let len = match precision {
-            Some(p) => {
-                if p < chars_count {
-                    p
-                } else {
-                    chars_count
-                }
+        // precision will limit length, find the width and bytes to write
+        let (len, end, reset) = if self.options().ansi() {
+            measure_ansi(s, self.width_mode(), self.precision())
+        } else {
+            let (len, end) = measure(s, self.width_mode(), self.precision());
+            (len, end, false)
+        };
+
+        let pad = self.pad_before(len)?;
+        self.write_str(&s[..end])?;
+        if reset {
+            self.write_str(ansi::RESET)?;
+        }
+        write_char(self, fill, pad)?;
+        Ok(())
+    }
+
+    /// write `s` cut at `cut` to fit in `precision` with `marker` in place of
+    /// the part that was cut.
+    ///
+    /// With [Options::with_ansi] escape sequences are kept whole: a reset is
+    /// written before the marker if the start was styled, and the escape
+    /// sequences of the part that was cut are written after it so the end
+    /// keeps its style.
+    fn str_ellipsis(&mut self, s: &str, precision: usize, cut: Cut, marker: &str) -> Result<()> {
+        let mode = self.width_mode();
+        let ansi = self.options().ansi();
+        let fill = self.fill();
+        let (marker_len, marker_end) = measure(marker, mode, Some(precision));
+        let keep = precision - marker_len;
+        let head = match cut {
+            Cut::Start => 0,
+            Cut::Middle => (keep + 1) / 2,
+            Cut::End => keep,
+        };
+        let (head_len, head_end, reset) = if head == 0 {
+            (0, 0, false)
+        } else if ansi {
+            measure_ansi(s, mode, Some(head))
+        } else {
+            let (len, end) = measure(s, mode, Some(head));
+            (len, end, false)
+        };
+        let (tail_len, tail_start) = match cut {
+            Cut::End => (0, s.len()),
+            _ if ansi => measure_ansi_back(s, mode, Some(keep - head_len)),
+            _ => measure_back(s, mode, Some(keep - head_len)),
+        };
+
+        let pad = self.pad_before(head_len + marker_len + tail_len)?;
+        self.write_str(&s[..head_end])?;
+        if reset {
+            self.write_str(ansi::RESET)?;
+        }
+        self.write_str(&marker[..marker_end])?;
+        if ansi && cut != Cut::End {
+            for escape in ansi::escapes(&s[head_end..tail_start]) {
+                self.write_str(escape)?;
+            }
+        }
+        self.write_str(&s[tail_start..])?;
+        write_char(self, fill, pad)?;
+        Ok(())
+    }
+
+    /// write `s` wrapped at word boundaries into lines of `width`, each one
+    /// filled and aligned. The lines after the first are indented by `indent`
+    /// spaces. Words longer than a line are split.
+    fn str_wrapped(&mut self, s: &str, width: usize, indent: usize) -> Result<()> {
+        let mode = self.width_mode();
+        let ansi = self.options().ansi();
+        // escape sequences have no width when ansi is enabled
+        let measure = |s: &str, precision: Option<usize>| {
+            if ansi {
+                let (len, end, _) = measure_ansi(s, mode, precision);
+                (len, end)
+            } else {
+                measure(s, mode, precision)
             }
-            None => chars_count,
         };
+        let indent = if indent < width { indent } else { 0 };
+        let mut lines = 0;
+        for paragraph in s.split('\n') {
+            // the line being filled: start, end and width
+            let mut line: Option<(usize, usize, usize)> = None;
+            for (start, end) in words(paragraph) {
+                let w = measure(&paragraph[start..end], None).0;
+                if let Some((line_start, line_end, line_width)) = line {
+                    let avail = if lines == 0 { width } else { width - indent };
+                    let gap = measure(&paragraph[line_end..start], None).0;
+                    if line_width + gap + w <= avail {
+                        line = Some((line_start, end, line_width + gap + w));
+                        continue;
+                    }
+                    self.wrap_line(&paragraph[line_start..line_end], line_width, lines, indent)?;
+                    lines += 1;
+                }
+                // start a new line with the word, splitting it if it is too long
+                let mut start = start;
+                loop {
+                    let avail = if lines == 0 { width } else { width - indent };
+                    let (mut w, mut split) = measure(&paragraph[start..end], Some(avail));
+                    if split == 0 {
+                        // a unit wider than the line, write it anyway
+                        let c = paragraph[start..].chars().next().unwrap();
+                        split = c.len_utf8();
+                        w = measure(&paragraph[start..start + split], None).0;
+                    }
+                    if start + split == end {
+                        line = Some((start, end, w));
+                        break;
+                    }
+                    self.wrap_line(&paragraph[start..start + split], w, lines, indent)?;
+                    lines += 1;
+                    start += split;
+                }
+            }
+            let (line_start, line_end, line_width) = line.unwrap_or((0, 0, 0));
+            self.wrap_line(&paragraph[line_start..line_end], line_width, lines, indent)?;
+            lines += 1;
+        }
+        Ok(())
+    }
+
+    /// write line number `n` of a wrapped value
+    fn wrap_line(&mut self, line: &str, len: usize, n: usize, indent: usize) -> Result<()> {
+        let mut width = self.width();
+        if n > 0 {
+            self.write_char('\n')?;
+            write_char(self, ' ', indent)?;
+            width = width.map(|w| w - indent);
+        }
+        let fill = self.fill();
+        let pad = self.pad_to(len, width)?;
+        self.write_str(line)?;
+        write_char(self, fill, pad)?;
+        Ok(())
+    }
+
+    /// write the fill needed before a value of `len` chars given the width
+    /// and alignment, returning the amount of fill needed after the value
+    pub(crate) fn pad_before(&mut self, len: usize) -> Result<usize> {
+        let width = self.width();
+        self.pad_to(len, width)
+    }
 
-        let mut chars = s.chars();
+    /// write the fill needed before a value of `len` chars to fill `width`
+    fn pad_to(&mut self, len: usize, width: Option<usize>) -> Result<usize> {
+        let fill = self.fill();
         let mut pad: usize = 0;
         if let Some(mut width) = width {
             if width > len {
@@ -135,11 +294,11 @@ impl<'a, 'b> Formatter<'a, 'b> {
                     Alignment::Center => {
                         width -= len;
                         pad = width / 2;
-                        write_char(self, fill, pad);
+                        write_char(self, fill, pad)?;
                         pad += width % 2;
                     }
                     Alignment::Right => {
-                        write_char(self, fill, width - len);
+                        write_char(self, fill, width - len)?;
                     }
                     Alignment::Equal => {
                         return Err(FmtError::Invalid(
@@ -150,79 +309,176 @@ impl<'a, 'b> Formatter<'a, 'b> {
                 }
             }
         }
-        write_from(self, &mut chars, len);
-        write_char(self, fill, pad);
+        Ok(pad)
+    }
+}
+
+/// the byte spans of the words of `s`, separated by spaces
+fn words(s: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut pos = 0;
+    s.split(' ').filter_map(move |word| {
+        let start = pos;
+        pos += word.len() + 1;
+        if word.is_empty() {
+            None
+        } else {
+            Some((start, start + word.len()))
+        }
+    })
+}
+
+/// a piece of a format string found by [scan]
+pub(crate) enum Token<'s> {
+    /// literal text, with `{{` and `}}` already unescaped
+    Literal(&'s str),
+    /// the pattern between braces, i.e. `key:spec`
+    Field(&'s str),
+}
+
+/// the span of literal text read by [scan] but not yet given to the callback
+pub(crate) struct Run {
+    start: usize,
+    end: usize,
+}
+
+impl Run {
+    pub(crate) fn new() -> Run {
+        Run { start: 0, end: 0 }
+    }
+
+    /// add `fmtstr[start..end]` to the run, flushing the run first if the
+    /// text does not follow it
+    pub(crate) fn push<'s, F>(
+        &mut self,
+        fmtstr: &'s str,
+        start: usize,
+        end: usize,
+        f: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(Token<'s>) -> Result<()>,
+    {
+        if self.end != start {
+            self.flush(fmtstr, f)?;
+            self.start = start;
+        }
+        self.end = end;
+        Ok(())
+    }
+
+    pub(crate) fn flush<'s, F>(&mut self, fmtstr: &'s str, f: &mut F) -> Result<()>
+    where
+        F: FnMut(Token<'s>) -> Result<()>,
+    {
+        if self.start < self.end {
+            f(Token::Literal(&fmtstr[self.start..self.end]))?;
+        }
+        self.start = self.end;
         Ok(())
     }
 }
 
-/// UNSTABLE: the Formatter object is still considered unstable
-/// Do not use this function if you aren't willing to have changes
-/// forced on you!
+/// `0x01` in every byte of a word
+const LO: u64 = 0x0101_0101_0101_0101;
+/// `0x80` in every byte of a word
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// return true if any byte of `word` is `b`
+fn has_byte(word: u64, b: u8) -> bool {
+    let x = word ^ (LO * b as u64);
+    x.wrapping_sub(LO) & !x & HI != 0
+}
+
+/// find the first `{` or `}` in `bytes`, checking 8 bytes at a time
+pub(crate) fn find_brace(bytes: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i + 8 <= bytes.len() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&bytes[i..i + 8]);
+        let word = u64::from_le_bytes(word);
+        if has_byte(word, b'{') || has_byte(word, b'}') {
+            break;
+        }
+        i += 8;
+    }
+    bytes[i..]
+        .iter()
+        .position(|&b| b == b'{' || b == b'}')
+        .map(|p| i + p)
+}
+
+#[test]
+fn test_find_brace() {
+    assert_eq!(find_brace(b""), None);
+    assert_eq!(find_brace(b"abcdefghijklmnop"), None);
+    assert_eq!(find_brace(b"ab{"), Some(2));
+    assert_eq!(find_brace(b"abcdefghijk}mnop"), Some(11));
+    assert_eq!(find_brace(b"abcdefg{"), Some(7));
+    assert_eq!(
+        find_brace("\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}}".as_bytes()),
+        Some(10)
+    );
+}
+
+/// read a format string, calling `f` with every run of literal text and field
 ///
-/// format a string given the string and a closure that uses
-/// a Formatter
-pub fn strfmt_map<F>(fmtstr: &str, f: F) -> Result<String>
+/// Braces are ASCII, so the text between them is found by searching the bytes
+/// and copied as a whole.
+pub(crate) fn scan<'s, F>(fmtstr: &'s str, f: F) -> Result<()>
 where
-    F: FnMut(Formatter) -> Result<()>,
+    F: FnMut(Token<'s>) -> Result<()>,
 {
     let mut f = f;
-    let mut out = String::with_capacity(fmtstr.len() * 2);
-    let mut bytes_read: usize = 0;
+    let bytes = fmtstr.as_bytes();
+    let mut run = Run::new();
     let mut opening_brace: usize = 0;
     let mut closing_brace: bool = false;
     let mut reading_fmt = false;
-    let mut remaining = fmtstr;
-    for c in fmtstr.chars() {
-        bytes_read += c.len_utf8();
-        if c == '{' {
-            if reading_fmt && opening_brace == bytes_read - 2 {
+    let mut i = 0;
+    while i < bytes.len() {
+        let brace = match find_brace(&bytes[i..]) {
+            Some(p) => i + p,
+            None => bytes.len(),
+        };
+        if brace > i {
+            // found text without braces
+            if closing_brace {
+                return Err(FmtError::Invalid(
+                    "Single '}' encountered in format string".to_string(),
+                ));
+            } else if !reading_fmt {
+                run.push(fmtstr, i, brace, &mut f)?;
+            } // else we are currently reading a format string, so don't push
+            i = brace;
+            continue;
+        }
+        if bytes[i] == b'{' {
+            if reading_fmt && opening_brace + 1 == i {
                 // found {{
-                out.push(c);
+                run.push(fmtstr, i, i + 1, &mut f)?;
                 reading_fmt = false;
             } else if !reading_fmt {
                 // found a first {
                 reading_fmt = true;
-                opening_brace = bytes_read - 1;
+                opening_brace = i;
             } else {
                 // found a { after finding an opening brace, error!
-                out.clear();
-                out.write_str("extra { found").unwrap();
-                return Err(FmtError::Invalid(out));
-            }
-        } else if c == '}' {
-            if !reading_fmt && !closing_brace {
-                // found a '}' that isn't after a '{'
-                closing_brace = true;
-            } else if closing_brace {
-                // found "}}"
-                out.push(c);
-                closing_brace = false;
-            } else {
-                // found a format string
-                // discard before opening brace
-                let (_, r) = remaining.split_at(opening_brace);
-
-                // get the fmt pattern and remaining
-                let (fmt_pattern, r) = r.split_at(bytes_read - opening_brace);
-                remaining = r;
-
-                // discard the braces
-                let (_, fmt_pattern) = fmt_pattern.split_at(1);
-                let (fmt_pattern, _) = fmt_pattern.split_at(fmt_pattern.len() - 1);
-                // use the closure to write the formatted string
-                let fmt = Formatter::from_str(fmt_pattern, &mut out)?;
-                f(fmt)?;
-                reading_fmt = false;
-                bytes_read = 0;
+                return Err(FmtError::Invalid("extra { found".to_string()));
             }
+        } else if !reading_fmt && !closing_brace {
+            // found a '}' that isn't after a '{'
+            closing_brace = true;
         } else if closing_brace {
-            return Err(FmtError::Invalid(
-                "Single '}' encountered in format string".to_string(),
-            ));
-        } else if !reading_fmt {
-            out.push(c)
-        } // else we are currently reading a format string, so don't push
+            // found "}}"
+            run.push(fmtstr, i, i + 1, &mut f)?;
+            closing_brace = false;
+        } else {
+            // found a format string, without the braces
+            run.flush(fmtstr, &mut f)?;
+            f(Token::Field(&fmtstr[opening_brace + 1..i]))?;
+            reading_fmt = false;
+        }
+        i += 1;
     }
     if closing_brace {
         return Err(FmtError::Invalid(
@@ -233,6 +489,124 @@ where
             "Expected '}' before end of string".to_string(),
         ));
     }
+    run.flush(fmtstr, &mut f)
+}
+
+#[test]
+fn test_scan() {
+    fn tokens(fmtstr: &str) -> Result<String> {
+        let mut out = String::new();
+        scan(fmtstr, |token| {
+            match token {
+                Token::Literal(s) => write!(out, "<{}>", s).unwrap(),
+                Token::Field(p) => write!(out, "[{}]", p).unwrap(),
+            }
+            Ok(())
+        })?;
+        Ok(out)
+    }
+    assert_eq!(tokens("").unwrap(), "");
+    assert_eq!(
+        tokens("a long line of text {x} more text {y:>5}").unwrap(),
+        "<a long line of text >[x]< more text >[y:>5]"
+    );
+    assert_eq!(
+        tokens("{{\u{e9}t\u{e9}}} {k\u{e9}y}!").unwrap(),
+        "<{\u{e9}t\u{e9}><} >[k\u{e9}y]<!>"
+    );
+    assert_eq!(tokens("{}{x}").unwrap(), "[][x]");
+    assert!(tokens("text {x").is_err());
+    assert!(tokens("text } more").is_err());
+    assert!(tokens("text {x{y}").is_err());
+}
+
+/// UNSTABLE: the Formatter object is still considered unstable
+/// Do not use this function if you aren't willing to have changes
+/// forced on you!
+///
+/// format a string given the string and a closure that uses
+/// a Formatter
+pub fn strfmt_map<F>(fmtstr: &str, f: F) -> Result<String>
+where
+    F: FnMut(Formatter) -> Result<()>,
+{
+    let mut out = String::with_capacity(fmtstr.len() * 2);
+    strfmt_map_write(fmtstr, f, &mut out)?;
     out.shrink_to_fit();
     Ok(out)
 }
+
+/// UNSTABLE: the Formatter object is still considered unstable
+///
+/// format a string onto any `fmt::Write` given the string and a closure
+/// that uses a Formatter. see [strfmt_map] for details
+///
+/// The output is written as the string is read, so on error `out` may contain
+/// part of the formatted string.
+///
+/// # Examples
+///
+/// ```
+/// use strfmt::{strfmt_map_write, Formatter};
+///
+/// let mut out = String::from("> ");
+/// strfmt_map_write("{x:^5}|", |mut fmt: Formatter| fmt.str("hi"), &mut out).unwrap();
+/// assert_eq!(out, ">  hi  |");
+/// ```
+pub fn strfmt_map_write<F, W>(fmtstr: &str, f: F, out: &mut W) -> Result<()>
+where
+    F: FnMut(Formatter) -> Result<()>,
+    W: fmt::Write,
+{
+    strfmt_map_write_with(fmtstr, Options::default(), f, out)
+}
+
+/// [strfmt_map_write] with the formatters created with `options`
+pub(crate) fn strfmt_map_write_with<F, W>(
+    fmtstr: &str,
+    options: Options,
+    f: F,
+    out: &mut W,
+) -> Result<()>
+where
+    F: FnMut(Formatter) -> Result<()>,
+    W: fmt::Write,
+{
+    let mut f = f;
+    let out: &mut dyn fmt::Write = out;
+    let mut line = Line::new();
+    let syntax = options.syntax();
+    let mut positions = Positions::new();
+    syntax.scan(fmtstr, |token| match token {
+        Token::Literal(s) => {
+            line.literal(s);
+            out.write_str(s)?;
+            Ok(())
+        }
+        // use the closure to write the formatted string
+        Token::Field(pattern) => {
+            let (identifier, raw_spec, stars) = syntax.split_field(pattern, &mut positions)?;
+            let (key, annotations) = split_annotations(&identifier)?;
+            // the spec is parsed on first use, unless `*` arguments set it
+            let mut fmt = if stars.is_empty() {
+                Formatter::from_raw(key, pattern, raw_spec, syntax, out)
+            } else {
+                let (mut spec, spec_error) = syntax.parse_spec(raw_spec);
+                stars.apply(&mut spec, &mut f)?;
+                Formatter::from_parsed(key, pattern, raw_spec, spec, spec_error, out)
+            };
+            fmt.set_annotations(annotations);
+            fmt.set_syntax(syntax);
+            fmt.set_options(options);
+            fmt.set_line(line);
+            line.field();
+            let style_started = Cell::new(false);
+            fmt.start_style_with(&style_started);
+            f(fmt)?;
+            if style_started.get() {
+                out.write_str(ansi::RESET)?;
+            }
+            Ok(())
+        }
+    })
+}
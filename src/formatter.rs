@@ -1,395 +1,507 @@
-use std::fmt;
-use std::fmt::Write;
-use std::iter::Iterator;
-use std::str;
-use std::string::String;
-
+use alloc::string::ToString;
+use core::cell::{Cell, Ref, RefCell};
+use core::fmt;
+
+use ansi::Style;
+use fmtnum::NumberStyle;
+use indent::{split_annotations, Annotations, Indent, Line};
+use options::Options;
+use spec::{parse_like_python, FormatSpec};
+use syntax::Syntax;
 use types::*;
+use width::WidthMode;
 
-#[derive(Debug, PartialEq)]
 pub struct Formatter<'a, 'b> {
     pub key: &'a str,
-    fill: char,
-    align: Alignment, // default Right for numbers, Left for strings
-    sign: Sign,
-    alternate: bool,
-    width: Option<usize>,
-    thousands: bool,
-    precision: Option<usize>,
-    ty: Option<char>,
-    buff: &'b mut String,
+    spec: RefCell<LazySpec>,
+    buff: &'b mut dyn fmt::Write,
     pattern: &'a str,
+    raw_spec: &'a str,
+    options: Options,
+    annotations: Annotations,
+    line: Line<'a>,
+    syntax: Syntax,
+    /// a newline was written, the indent is written before the next text
+    indent_pending: bool,
+    /// set once the start of the style is written, before the first output
+    style_started: Option<&'b Cell<bool>>,
 }
 
-fn is_alignment_token(c: char) -> bool {
-    match c {
-        '=' | '<' | '^' | '>' => true,
-        _ => false,
-    }
+/// the spec of a Formatter, parsed from the raw spec on first use so that a
+/// [DisplayStr](crate::DisplayStr) reading only the raw spec never parses it
+#[derive(Debug)]
+enum LazySpec {
+    /// not parsed yet, with the syntax to parse it with
+    Raw(Syntax),
+    /// the parsed spec and the error found parsing it
+    Parsed(FormatSpec, Option<FmtError>),
 }
 
-fn is_sign_element(c: char) -> bool {
-    match c {
-        ' ' | '-' | '+' => true,
-        _ => false,
+/// spaces written at once to indent a line
+const SPACES: &str = "                ";
+
+/// split the identifier from the spec of `key:spec`
+pub(crate) fn split_pattern(s: &str) -> Result<(&str, &str)> {
+    let mut found_colon = false;
+    let mut chars = s.chars();
+    let mut c = match chars.next() {
+        Some(':') | None => return Err(FmtError::Invalid("must specify identifier".to_string())),
+        Some(c) => c,
+    };
+    let mut consumed = 0;
+    // find the identifier
+    loop {
+        consumed += c.len_utf8();
+        if c == ':' {
+            found_colon = true;
+            break;
+        }
+        c = match chars.next() {
+            Some(c) => c,
+            None => {
+                break;
+            }
+        };
     }
+    let (identifier, rest) = s.split_at(consumed);
+    let identifier = if found_colon {
+        let (i, _) = identifier.split_at(identifier.len() - 1); // get rid of ':'
+        i
+    } else {
+        identifier
+    };
+    Ok((identifier, rest))
 }
 
-fn is_type_element(c: char) -> bool {
-    match c {
-        'b' | 'o' | 'x' | 'X' | 'e' | 'E' | 'f' | 'F' | '%' | 's' | '?' => true,
-        _ => false,
+/// parse a spec with the standard syntax, keeping the error instead of failing
+pub(crate) fn parse_spec(raw_spec: &str) -> (FormatSpec, Option<FmtError>) {
+    match parse_like_python(raw_spec) {
+        Ok(spec) => (spec, None),
+        Err(e) => (FormatSpec::default(), Some(e)),
     }
 }
 
-// get an integer from pos, returning the number of bytes
-// consumed and the integer
-fn get_integer(s: &[u8], pos: usize) -> (usize, Option<i64>) {
-    let (_, rest) = s.split_at(pos);
-    let mut consumed: usize = 0;
-    for b in rest {
-        match *b as char {
-            '0'..='9' => {}
-            _ => break,
-        };
-        consumed += 1;
+impl<'a, 'b> Formatter<'a, 'b> {
+    /// create Formatter from format string
+    pub fn from_str(s: &'a str, buff: &'b mut dyn fmt::Write) -> Result<Formatter<'a, 'b>> {
+        let fmt = Formatter::from_pattern(s, buff)?;
+        fmt.check_spec()?;
+        Ok(fmt)
+    }
+
+    /// create Formatter from format string, only failing if there is no identifier.
+    ///
+    /// If the spec does not follow the standard syntax the error is kept
+    /// and returned by the builtin formatting methods, so that a custom
+    /// [DisplayStr](crate::DisplayStr) can still use [Formatter::raw_spec]
+    pub(crate) fn from_pattern(
+        s: &'a str,
+        buff: &'b mut dyn fmt::Write,
+    ) -> Result<Formatter<'a, 'b>> {
+        let (identifier, rest) = split_pattern(s)?;
+        let (key, annotations) = split_annotations(identifier)?;
+        let mut fmt = Formatter::from_parts(key, s, rest, buff);
+        fmt.annotations = annotations;
+        Ok(fmt)
+    }
+
+    /// create Formatter from a key, a spec and any output sink
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strfmt::{FormatSpec, Formatter};
+    ///
+    /// let spec: FormatSpec = ">6".parse().unwrap();
+    /// let mut out = String::new();
+    /// Formatter::new("x", spec, &mut out).str("abc").unwrap();
+    /// assert_eq!(out, "   abc");
+    /// ```
+    pub fn new(key: &'a str, spec: FormatSpec, buff: &'b mut dyn fmt::Write) -> Formatter<'a, 'b> {
+        Formatter {
+            key,
+            spec: RefCell::new(LazySpec::Parsed(spec, None)),
+            buff,
+            pattern: "",
+            raw_spec: "",
+            options: Options::default(),
+            annotations: Annotations::default(),
+            line: Line::new(),
+            syntax: Syntax::Braces,
+            indent_pending: false,
+            style_started: None,
+        }
     }
-    if consumed == 0 {
-        (0, None)
-    } else {
-        let (intstr, _) = rest.split_at(consumed);
-        let val = unsafe {
-            // I think I can be reasonably sure that 0-9 chars are utf8 :)
-            match str::from_utf8_unchecked(intstr).parse::<i64>() {
-                Ok(v) => Some(v),
-                Err(_) => None,
-            }
-        };
-        (consumed, val)
+
+    /// create Formatter from an already split identifier and spec
+    pub(crate) fn from_parts(
+        key: &'a str,
+        pattern: &'a str,
+        raw_spec: &'a str,
+        buff: &'b mut dyn fmt::Write,
+    ) -> Formatter<'a, 'b> {
+        Formatter::from_raw(key, pattern, raw_spec, Syntax::Braces, buff)
+    }
+
+    /// create Formatter from a spec written in `syntax`, which is parsed
+    /// on first use
+    pub(crate) fn from_raw(
+        key: &'a str,
+        pattern: &'a str,
+        raw_spec: &'a str,
+        syntax: Syntax,
+        buff: &'b mut dyn fmt::Write,
+    ) -> Formatter<'a, 'b> {
+        Formatter {
+            key,
+            spec: RefCell::new(LazySpec::Raw(syntax)),
+            buff,
+            pattern,
+            raw_spec,
+            options: Options::default(),
+            annotations: Annotations::default(),
+            line: Line::new(),
+            syntax,
+            indent_pending: false,
+            style_started: None,
+        }
     }
-}
 
-#[derive(Debug)]
-/// The format struct as it is defined in the python source
-struct FmtPy {
-    pub fill: char,
-    pub align: char,
-    pub alternate: bool,
-    pub sign: char,
-    pub width: i64,
-    pub thousands: bool,
-    pub precision: i64,
-    pub ty: char,
-}
+    /// create Formatter from an already parsed spec
+    pub(crate) fn from_parsed(
+        key: &'a str,
+        pattern: &'a str,
+        raw_spec: &'a str,
+        spec: FormatSpec,
+        spec_error: Option<FmtError>,
+        buff: &'b mut dyn fmt::Write,
+    ) -> Formatter<'a, 'b> {
+        Formatter {
+            key,
+            spec: RefCell::new(LazySpec::Parsed(spec, spec_error)),
+            buff,
+            pattern,
+            raw_spec,
+            options: Options::default(),
+            annotations: Annotations::default(),
+            line: Line::new(),
+            syntax: Syntax::Braces,
+            indent_pending: false,
+            style_started: None,
+        }
+    }
 
-fn parse_like_python(rest: &str) -> Result<FmtPy> {
-    // The rest of this was pretty much strait up copied from python's format parser
-    // All credit goes to python source file: formatter_unicode.c
-    //
-
-    let mut format = FmtPy {
-        fill: ' ',
-        align: '\0',
-        alternate: false,
-        sign: '\0',
-        width: -1,
-        thousands: false,
-        precision: -1,
-        ty: '\0',
-    };
-    let mut chars = rest.chars();
-    let fake_fill = match chars.next() {
-        Some(c) => c,
-        None => return Ok(format),
-    };
-    // from now on all format characters MUST be valid
-    // ASCII characters (fill and identifier were the
-    // only ones that weren't.
-    // Therefore we can use bytes for the rest
-    let rest = rest.as_bytes();
-    let mut align_specified = false;
-    let mut fill_specified = false;
-
-    let end: usize = rest.len();
-    let mut pos: usize = 0;
-
-    // If the second char is an alignment token,
-    // then fake_fill as fill
-    if end - pos >= 1 + fake_fill.len_utf8()
-        && is_alignment_token(rest[pos + fake_fill.len_utf8()] as char)
+    /// replace the spec of this Formatter, keeping the key and output buffer
+    pub(crate) fn with_spec<'c>(self, spec: &'c str) -> Formatter<'c, 'b>
+    where
+        'a: 'c,
     {
-        format.align = rest[pos + fake_fill.len_utf8()] as char;
-        format.fill = fake_fill;
-        fill_specified = true;
-        align_specified = true;
-        pos += 1 + fake_fill.len_utf8();
-    } else if end - pos >= 1 && is_alignment_token(fake_fill) {
-        format.align = fake_fill;
-        pos += fake_fill.len_utf8();
-    }
-
-    // Parse the various sign options
-    if end - pos >= 1 && is_sign_element(rest[pos] as char) {
-        format.sign = rest[pos] as char;
-        pos += 1;
-    }
-
-    // If the next character is #, we're in alternate mode.  This only
-    // applies to integers.
-    if end - pos >= 1 && rest[pos] as char == '#' {
-        format.alternate = true;
-        pos += 1;
-    }
-
-    // The special case for 0-padding (backwards compat)
-    if !fill_specified && end - pos >= 1 && rest[pos] == '0' as u8 {
-        format.fill = '0';
-        if !align_specified {
-            format.align = '=';
-        }
-        pos += 1;
+        let mut fmt = Formatter::from_parts(self.key, self.pattern, spec, self.buff);
+        fmt.options = self.options;
+        fmt.annotations = self.annotations;
+        fmt.line = self.line;
+        fmt.syntax = self.syntax;
+        fmt.style_started = self.style_started;
+        fmt
     }
 
-    // check to make sure that val is good
-    let (consumed, val) = get_integer(rest, pos);
-    pos += consumed;
-    if consumed != 0 {
-        match val {
-            None => {
-                return Err(FmtError::Invalid(
-                    "overflow error when parsing width".to_string(),
-                ))
-            }
-            Some(v) => {
-                format.width = v;
-            }
-        }
+    /// the options of the render, see [Options]
+    pub fn options(&self) -> Options {
+        self.options
     }
 
-    // Comma signifies add thousands separators
-    if end - pos > 0 && rest[pos] as char == ',' {
-        format.thousands = true;
-        pos += 1;
+    /// set the options of the render, i.e. from the closure given to
+    /// [strfmt_map](crate::strfmt_map)
+    pub fn set_options(&mut self, options: Options) {
+        self.options = options;
     }
 
-    // Parse field precision
-    if end - pos > 0 && rest[pos] as char == '.' {
-        pos += 1;
+    /// set the annotations of the field
+    pub(crate) fn set_annotations(&mut self, annotations: Annotations) {
+        self.annotations = annotations;
+    }
 
-        let (consumed, val) = get_integer(rest, pos);
-        if consumed != 0 {
-            match val {
-                None => {
-                    return Err(FmtError::Invalid(
-                        "overflow error when parsing precision".to_string(),
-                    ))
-                }
-                Some(v) => {
-                    format.precision = v;
-                }
-            }
-        } else {
-            // Not having a precision after a dot is an error.
-            if consumed == 0 {
-                return Err(FmtError::Invalid(
-                    "Format specifier missing precision".to_string(),
-                ));
-            }
-        }
-        pos += consumed;
+    /// set the syntax the field was written in, used by [Formatter::skip]
+    pub(crate) fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    /// the syntax the field was written in
+    pub(crate) fn syntax(&self) -> Syntax {
+        self.syntax
+    }
+
+    /// like C and python, the `c` and float types are valid for integers
+    pub(crate) fn c_ints(&self) -> bool {
+        self.syntax == Syntax::Percent || self.options.python()
+    }
+
+    /// set where the field is on its line, used to indent multi-line values
+    pub(crate) fn set_line(&mut self, line: Line<'a>) {
+        self.line = line;
     }
 
-    // Finally, parse the type field.
-    if end - pos > 1 {
-        // More than one char remain, invalid format specifier.
-        return Err(FmtError::Invalid("Invalid format specifier".to_string()));
+    /// how multi-line values are indented: the `!indent` annotation of the
+    /// field if it has one, else the indent of the render
+    pub fn indent(&self) -> Indent {
+        match self.annotations.indent {
+            Some(indent) => indent,
+            None => self.options.indent(),
+        }
     }
 
-    if end - pos == 1 {
-        format.ty = rest[pos] as char;
-        if !is_type_element(format.ty) {
-            let mut msg = String::new();
-            write!(msg, "Invalid type specifier: {:?}", format.ty).unwrap();
-            return Err(FmtError::TypeError(msg));
+    /// the style of the field from its `!style=` annotation, unless the
+    /// options strip styles
+    pub(crate) fn style(&self) -> Option<Style> {
+        match self.options.styles() {
+            true => self.annotations.style,
+            false => None,
         }
-        // pos+=1;
     }
 
-    // Do as much validating as we can, just by looking at the format
-    // specifier.  Do not take into account what type of formatting
-    // we're doing (int, float, string).
-    if format.thousands {
-        match format.ty {
-            'd' | 'e' | 'f' | 'g' | 'E' | 'G' | '%' | 'F' | '\0' => {} /* These are allowed. See PEP 378.*/
+    /// write the style of the field before its first output, setting
+    /// `started` once it is written so the caller knows to reset it. Nothing
+    /// is written for a field with no output or one that is skipped.
+    pub(crate) fn start_style_with(&mut self, started: &'b Cell<bool>) {
+        self.style_started = Some(started);
+    }
 
-            _ => {
-                let mut msg = String::new();
-                write!(msg, "Invalid comma type: {}", format.ty).unwrap();
-                return Err(FmtError::Invalid(msg));
+    /// write the start of the style if it was not written yet
+    fn start_style(&mut self) -> fmt::Result {
+        if let (Some(style), Some(started)) = (self.style(), self.style_started) {
+            if !started.get() {
+                started.set(true);
+                style.write_start(self.buff)?;
             }
         }
+        Ok(())
     }
-    Ok(format)
-}
 
-impl<'a, 'b> Formatter<'a, 'b> {
-    /// create Formatter from format string
-    pub fn from_str(s: &'a str, buff: &'b mut String) -> Result<Formatter<'a, 'b>> {
-        let mut found_colon = false;
-        let mut chars = s.chars();
-        let mut c = match chars.next() {
-            Some(':') | None => {
-                return Err(FmtError::Invalid("must specify identifier".to_string()))
+    /// write `s`, indenting the lines after the first
+    fn write_indented(&mut self, s: &str, indent: Indent) -> fmt::Result {
+        for (i, part) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.buff.write_char('\n')?;
+                self.indent_pending = true;
             }
-            Some(c) => c,
-        };
-        let mut consumed = 0;
-        // find the identifier
-        loop {
-            consumed += c.len_utf8();
-            if c == ':' {
-                found_colon = true;
-                break;
+            if part.is_empty() {
+                continue;
             }
-            c = match chars.next() {
-                Some(c) => c,
-                None => {
-                    break;
+            if self.indent_pending {
+                self.indent_pending = false;
+                match indent {
+                    Indent::Column => {
+                        let mut n = self.line.column;
+                        while n > 0 {
+                            let count = n.min(SPACES.len());
+                            self.buff.write_str(&SPACES[..count])?;
+                            n -= count;
+                        }
+                    }
+                    Indent::Leading => self.buff.write_str(self.line.leading)?,
+                    Indent::Off => {}
                 }
-            };
+            }
+            self.buff.write_str(part)?;
         }
-        let (identifier, rest) = s.split_at(consumed);
-        let identifier = if found_colon {
-            let (i, _) = identifier.split_at(identifier.len() - 1); // get rid of ':'
-            i
-        } else {
-            identifier
+        Ok(())
+    }
+
+    /// the raw text of the format spec, everything after the first ':'.
+    /// This is empty if the Formatter was created with [Formatter::new].
+    ///
+    /// This lets a [DisplayStr](crate::DisplayStr) accept its own
+    /// spec syntax (i.e. `{when:%Y-%m-%d}`) instead of the standard one.
+    pub fn raw_spec(&self) -> &'a str {
+        self.raw_spec
+    }
+
+    /// the parsed spec, empty if the raw spec did not follow the standard syntax
+    pub fn spec(&self) -> FormatSpec {
+        self.parsed().clone()
+    }
+
+    /// return the error found when parsing the spec with the standard syntax,
+    /// or for a [WidthMode] that needs the `unicode` feature without it.
+    /// This is checked by all builtin formatting methods (`str`, `i64`, etc).
+    pub fn check_spec(&self) -> Result<()> {
+        self.parse();
+        if let LazySpec::Parsed(_, Some(ref e)) = *self.spec.borrow() {
+            return Err(e.clone());
+        }
+        #[cfg(not(feature = "unicode"))]
+        {
+            if self.width_mode() != WidthMode::Chars {
+                return Err(FmtError::Invalid(
+                    "Width modes other than chars require the unicode feature".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// parse the raw spec if it was not parsed yet
+    fn parse(&self) {
+        let syntax = match *self.spec.borrow() {
+            LazySpec::Raw(syntax) => syntax,
+            LazySpec::Parsed(..) => return,
         };
+        let (spec, spec_error) = syntax.parse_spec(self.raw_spec);
+        *self.spec.borrow_mut() = LazySpec::Parsed(spec, spec_error);
+    }
 
-        let format = parse_like_python(rest)?;
-
-        Ok(Formatter {
-            key: identifier,
-            fill: format.fill,
-            align: match format.align {
-                '\0' => Alignment::Unspecified,
-                '<' => Alignment::Left,
-                '^' => Alignment::Center,
-                '>' => Alignment::Right,
-                '=' => Alignment::Equal,
-                _ => unreachable!(),
-            },
-            sign: match format.sign {
-                '\0' => Sign::Unspecified,
-                '+' => Sign::Plus,
-                '-' => Sign::Minus,
-                ' ' => Sign::Space,
-                _ => unreachable!(),
-            },
-            alternate: format.alternate,
-            width: match format.width {
-                -1 => None,
-                _ => Some(format.width as usize),
-            },
-            thousands: format.thousands,
-            precision: match format.precision {
-                -1 => None,
-                _ => Some(format.precision as usize),
-            },
-            ty: match format.ty {
-                '\0' => None,
-                _ => Some(format.ty),
-            },
-            buff: buff,
-            pattern: s,
+    /// the parsed spec, parsing it on first use
+    fn parsed(&self) -> Ref<'_, FormatSpec> {
+        self.parse();
+        Ref::map(self.spec.borrow(), |spec| match *spec {
+            LazySpec::Parsed(ref spec, _) => spec,
+            LazySpec::Raw(_) => unreachable!(),
         })
     }
 
+    /// the parsed spec to change, parsing it on first use
+    fn parsed_mut(&mut self) -> &mut FormatSpec {
+        self.parse();
+        match *self.spec.get_mut() {
+            LazySpec::Parsed(ref mut spec, _) => spec,
+            LazySpec::Raw(_) => unreachable!(),
+        }
+    }
+
     /// call this to re-write the original format string verbatum
     /// back to the output
-    pub fn skip(mut self) -> Result<()> {
-        self.buff.push('{');
-        self.write_str(self.pattern).unwrap();
-        self.buff.push('}');
+    pub fn skip(self) -> Result<()> {
+        let (open, close) = self.syntax.delimiters();
+        self.buff.write_str(open)?;
+        if self.pattern.is_empty() {
+            self.buff.write_str(self.key)?;
+            let spec = self.spec();
+            if spec != FormatSpec::default() {
+                write!(self.buff, ":{}", spec)?;
+            }
+        } else {
+            self.buff.write_str(self.pattern)?;
+        }
+        self.buff.write_str(close)?;
         Ok(())
     }
 
     /// fill getter
     pub fn fill(&self) -> char {
-        self.fill
+        self.parsed().fill()
     }
 
     /// align getter
     pub fn align(&self) -> Alignment {
-        self.align.clone()
+        self.parsed().align()
     }
 
     // provide default for unspecified alignment
     pub fn set_default_align(&mut self, align: Alignment) {
-        if self.align == Alignment::Unspecified {
-            self.align = align
+        let spec = self.parsed_mut();
+        if spec.align() == Alignment::Unspecified {
+            spec.align = align
         }
     }
 
     /// width getter
     pub fn width(&self) -> Option<usize> {
-        self.width
+        self.parsed().width
     }
 
     /// thousands getter
     pub fn thousands(&self) -> bool {
-        self.thousands
+        self.parsed().grouping.is_some()
+    }
+
+    /// grouping getter, the thousands separator (`,` or `_`)
+    pub fn grouping(&self) -> Option<char> {
+        self.parsed().grouping
+    }
+
+    /// the style of the digits of numbers
+    pub(crate) fn number_style(&self) -> NumberStyle {
+        self.parsed().number
     }
 
     /// precision getter
     pub fn precision(&self) -> Option<usize> {
-        self.precision
+        self.parsed().precision
     }
 
     /// set precision to None, used for formatting int, float, etc
     pub fn set_precision(&mut self, precision: Option<usize>) {
-        self.precision = precision;
+        self.parsed_mut().precision = precision;
+    }
+
+    /// how the width and precision of strings are measured: the mode of
+    /// the spec if it has one, else of the `!width` annotation of the field,
+    /// else the mode of the render
+    pub fn width_mode(&self) -> WidthMode {
+        self.parsed()
+            .width_mode
+            .or(self.annotations.width)
+            .unwrap_or_else(|| self.options.width_mode())
+    }
+
+    /// ellipsis getter, see [FormatSpec::ellipsis]
+    pub fn ellipsis(&self) -> Option<(Cut, &'static str)> {
+        self.parsed().ellipsis
+    }
+
+    /// bit width getter, see [FormatSpec::bits]
+    pub fn bits(&self) -> Option<u32> {
+        self.parsed().bits
+    }
+
+    /// wrap getter, see [FormatSpec::wrap]
+    pub fn wrap(&self) -> Option<usize> {
+        self.parsed().wrap
     }
 
     /// sign getter
     pub fn sign(&self) -> Sign {
-        self.sign.clone()
+        self.parsed().sign()
     }
 
     /// sign plus getter
     /// here because it is in fmt::Formatter
     pub fn sign_plus(&self) -> bool {
-        self.sign == Sign::Plus
+        self.parsed().sign == Sign::Plus
     }
 
     /// sign minus getter
     /// here because it is in fmt::Formatter
     pub fn sign_minus(&self) -> bool {
-        self.sign == Sign::Minus
+        self.parsed().sign == Sign::Minus
+    }
+
+    /// negative zero coercion (`z`) getter
+    pub fn positive_zero(&self) -> bool {
+        self.parsed().positive_zero
     }
 
     /// alternate getter
     pub fn alternate(&self) -> bool {
-        self.alternate
+        self.parsed().alternate
     }
 
     // sign_aware_zero_pad // Not supported
 
     /// type getter
     pub fn ty(&self) -> Option<char> {
-        self.ty
+        self.parsed().ty
     }
 
     /// UNSTABLE: in the future, this may return true if all validty
     ///   checks for a float return true
     /// return true if ty is valid for formatting integers
     pub fn is_int_type(&self) -> bool {
-        match self.ty {
+        match self.parsed().ty {
             None => true,
-            Some(c) => match c {
-                'b' | 'o' | 'x' | 'X' => true,
-                _ => false,
-            },
+            Some('?') => self.syntax == Syntax::Rust,
+            Some('c') => self.c_ints(),
+            Some(c) => matches!(c, 'b' | 'o' | 'x' | 'X' | 'd' | 'n'),
         }
     }
 
@@ -397,18 +509,33 @@ impl<'a, 'b> Formatter<'a, 'b> {
     ///   checks for a float return true
     /// return true if ty is valid for formatting floats
     pub fn is_float_type(&self) -> bool {
-        match self.ty {
+        match self.parsed().ty {
             None => true,
-            Some(c) => match c {
-                'f' | 'e' | 'E' => true,
-                _ => false,
-            },
+            Some('?') => self.syntax == Syntax::Rust,
+            Some(c) => matches!(c, 'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'n' | '%'),
         }
     }
 }
 
+impl<'a, 'b> fmt::Debug for Formatter<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Formatter")
+            .field("key", &self.key)
+            .field("spec", &self.spec)
+            .field("raw_spec", &self.raw_spec)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
 impl<'a, 'b> fmt::Write for Formatter<'a, 'b> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.buff.write_str(s)
+        if !s.is_empty() {
+            self.start_style()?;
+        }
+        match self.indent() {
+            Indent::Off => self.buff.write_str(s),
+            indent => self.write_indented(s, indent),
+        }
     }
 }
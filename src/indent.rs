@@ -0,0 +1,146 @@
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+
+use ansi::Style;
+use types::*;
+use width::WidthMode;
+
+/// How the lines after the first of a multi-line value are indented.
+///
+/// Set for a whole render with [Options::with_indent](crate::Options::with_indent)
+/// or for a single field with the `!indent` annotation: `{body!indent}` or
+/// `{body!indent=leading}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Indent {
+    /// written as they are, the default
+    #[default]
+    Off,
+    /// indented with spaces to the column of the field, counted in chars
+    /// of the literal text before the field on its line
+    Column,
+    /// indented with the leading whitespace of the line of the field
+    Leading,
+}
+
+/// the annotations of a field, `key!name=value` before the spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Annotations {
+    pub(crate) indent: Option<Indent>,
+    pub(crate) style: Option<Style>,
+    pub(crate) width: Option<WidthMode>,
+}
+
+impl fmt::Display for Annotations {
+    /// write the `!name=value` text, which splits back to equal annotations
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.indent {
+            None => {}
+            Some(Indent::Column) => f.write_str("!indent")?,
+            Some(Indent::Leading) => f.write_str("!indent=leading")?,
+            Some(Indent::Off) => f.write_str("!indent=off")?,
+        }
+        if let Some(style) = self.style {
+            write!(f, "!style={}", style)?;
+        }
+        match self.width {
+            None => {}
+            Some(WidthMode::Chars) => f.write_str("!width=chars")?,
+            Some(WidthMode::Graphemes) => f.write_str("!width=graphemes")?,
+            Some(WidthMode::Columns) => f.write_str("!width=columns")?,
+        }
+        Ok(())
+    }
+}
+
+/// split the annotations from the key of `key!name=value!name`. An
+/// identifier with an unknown annotation name is a key of its own, so keys
+/// like `a!b` keep working.
+pub(crate) fn split_annotations(identifier: &str) -> Result<(&str, Annotations)> {
+    let mut annotations = Annotations::default();
+    let mut parts = identifier.split('!');
+    let key = parts.next().unwrap_or("");
+    if key.is_empty() {
+        return Err(FmtError::Invalid("must specify identifier".to_string()));
+    }
+    for part in parts {
+        let (name, value) = match part.find('=') {
+            Some(i) => (&part[..i], Some(&part[i + 1..])),
+            None => (part, None),
+        };
+        match name {
+            "indent" => {
+                annotations.indent = Some(match value {
+                    None | Some("column") => Indent::Column,
+                    Some("leading") => Indent::Leading,
+                    Some("off") => Indent::Off,
+                    Some(v) => {
+                        let mut msg = String::new();
+                        write!(msg, "Invalid indent: {}", v).unwrap();
+                        return Err(FmtError::Invalid(msg));
+                    }
+                })
+            }
+            "style" => annotations.style = Some(Style::parse(value.unwrap_or(""))?),
+            "width" => {
+                annotations.width = Some(match value {
+                    Some("chars") => WidthMode::Chars,
+                    Some("graphemes") => WidthMode::Graphemes,
+                    Some("columns") => WidthMode::Columns,
+                    v => {
+                        let mut msg = String::new();
+                        write!(msg, "Invalid width mode: {}", v.unwrap_or("")).unwrap();
+                        return Err(FmtError::Invalid(msg));
+                    }
+                })
+            }
+            _ => return Ok((identifier, Annotations::default())),
+        }
+    }
+    Ok((key, annotations))
+}
+
+/// where a field is on its line, found from the literal text before it.
+/// The output of other fields on the line is not counted.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Line<'s> {
+    pub(crate) column: usize,
+    pub(crate) leading: &'s str,
+    in_leading: bool,
+}
+
+impl<'s> Line<'s> {
+    pub(crate) fn new() -> Line<'s> {
+        Line {
+            column: 0,
+            leading: "",
+            in_leading: true,
+        }
+    }
+
+    /// move past literal text
+    pub(crate) fn literal(&mut self, s: &'s str) {
+        let rest = match s.rfind('\n') {
+            Some(i) => {
+                self.column = 0;
+                self.leading = "";
+                self.in_leading = true;
+                &s[i + 1..]
+            }
+            None => s,
+        };
+        self.column += rest.chars().count();
+        if self.in_leading {
+            let end = rest.find(|c| c != ' ' && c != '\t').unwrap_or(rest.len());
+            // whitespace is never split between literal runs
+            if self.leading.is_empty() {
+                self.leading = &rest[..end];
+            }
+            self.in_leading = end == rest.len();
+        }
+    }
+
+    /// move past a field
+    pub(crate) fn field(&mut self) {
+        self.in_leading = false;
+    }
+}
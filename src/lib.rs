@@ -1,25 +1,75 @@
 //! strfmt crate
+//!
+//! The default `std` feature can be disabled to use the crate on `no_std`
+//! targets with `alloc`. Variables are then given as a `BTreeMap` (or any
+//! other [Vars]) and the `io` and `HashMap` functions are not available.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "unicode")]
+extern crate unicode_segmentation;
+#[cfg(feature = "unicode")]
+extern crate unicode_width;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use core::str::FromStr;
+
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::fmt;
-use std::fmt::Write;
-use std::hash::Hash;
-use std::str::FromStr;
-use std::string::String;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::io;
 
+mod ansi;
 mod fmtstr;
 mod formatter;
-#[cfg(test)]
+mod indent;
+#[cfg(all(test, feature = "std"))]
 mod tests;
 mod types;
 
+#[cfg(feature = "std")]
+mod cache;
 #[macro_use]
 mod fmtnum;
 mod macros;
+mod options;
+mod registry;
+mod sink;
+mod spec;
+mod syntax;
+mod template;
+mod translate;
+mod width;
 
-pub use fmtstr::strfmt_map;
+#[cfg(feature = "std")]
+pub use cache::TemplateCache;
+pub use fmtstr::{strfmt_map, strfmt_map_write};
 pub use formatter::Formatter;
-pub use types::{Alignment, FmtError, Result, Sign};
+pub use indent::Indent;
+pub use options::Options;
+pub use registry::{CustomFormat, Registry};
+pub use spec::{FormatSpec, FormatSpecBuilder};
+pub use syntax::Syntax;
+pub use template::{render_into_slice, RenderEach, Template};
+pub use translate::{LossyField, Translation};
+pub use types::{Alignment, Cut, FmtError, Result, Sign};
+pub use width::WidthMode;
+
+use fmtnum::NumBuf;
+use fmtstr::find_brace;
+#[cfg(feature = "std")]
+use sink::IoWrite;
 
 // u128 & i128 unstable (see https://github.com/rust-lang/rust/issues/35118)
 fmtint!(u8 i8 u16 i16 u32 i32 u64 i64 usize isize);
@@ -29,7 +79,7 @@ fmtfloat!(f32 f64);
 /// # Arguments
 ///
 /// * `fmtstr` - A string defining the format
-/// * `vars` - A `HashMap` holding the variables to use
+/// * `vars` - A `HashMap`, `BTreeMap` or other [Vars] holding the variables to use
 ///
 /// # Exceptions
 ///
@@ -50,56 +100,207 @@ fmtfloat!(f32 f64);
 ///
 /// println!("{}", strfmt("{Alpha} {Beta:<5.2}",&my_vars).unwrap());
 /// ```
-pub fn strfmt<'a, K, T: DisplayStr>(fmtstr: &str, vars: &HashMap<K, T>) -> Result<String>
-where
-    K: Hash + Eq + FromStr,
-{
-    let formatter = |mut fmt: Formatter| {
-        let k: K = match fmt.key.parse() {
-            Ok(k) => k,
-            Err(_) => {
-                return Err(new_key_error(fmt.key));
-            }
-        };
-        let v = match vars.get(&k) {
-            Some(v) => v,
-            None => {
-                return Err(new_key_error(fmt.key));
-            }
-        };
-        v.display_str(&mut fmt)
-    };
+pub fn strfmt<V: Vars + ?Sized>(fmtstr: &str, vars: &V) -> Result<String> {
+    let formatter = |mut fmt: Formatter| vars.display_var(&mut fmt);
     strfmt_map(fmtstr, &formatter)
 }
 
+/// Rust-style format a string given a `HashMap` of the variables and [Options]
+/// for the whole render.
+/// see [strfmt] for details
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use strfmt::{strfmt_with, Options, WidthMode};
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("name".to_string(), "bob");
+///
+/// let options = Options::new().with_width_mode(WidthMode::Chars);
+/// assert_eq!(strfmt_with("{name:>5}", &vars, options).unwrap(), "  bob");
+/// ```
+pub fn strfmt_with<V: Vars + ?Sized>(fmtstr: &str, vars: &V, options: Options) -> Result<String> {
+    let mut out = String::with_capacity(fmtstr.len() * 2);
+    let formatter = |mut fmt: Formatter| vars.display_var(&mut fmt);
+    fmtstr::strfmt_map_write_with(fmtstr, options, formatter, &mut out)?;
+    Ok(out)
+}
+
+/// Rust-style format a string given a `HashMap` of the variables, borrowing
+/// `fmtstr` instead of copying it if it has no fields and no `{{` or `}}` escapes.
+/// see [strfmt] for details
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use std::collections::HashMap;
+/// use strfmt::strfmt_cow;
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("name".to_string(), "bob");
+///
+/// assert!(matches!(strfmt_cow("plain text", &vars).unwrap(), Cow::Borrowed("plain text")));
+/// assert_eq!(strfmt_cow("hi {name}", &vars).unwrap(), "hi bob");
+/// ```
+pub fn strfmt_cow<'s, V: Vars + ?Sized>(fmtstr: &'s str, vars: &V) -> Result<Cow<'s, str>> {
+    match find_brace(fmtstr.as_bytes()) {
+        None => Ok(Cow::Borrowed(fmtstr)),
+        Some(_) => strfmt(fmtstr, vars).map(Cow::Owned),
+    }
+}
+
+/// Rust-style format a string given a `HashMap` of the variables, writing
+/// the output to any `fmt::Write`.
+/// see [strfmt] for details
+///
+/// The output is written as the string is read, so on error `out` may contain
+/// part of the formatted string.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::fmt::Write;
+/// use strfmt::strfmt_write;
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("name".to_string(), "bob");
+///
+/// let mut out = String::new();
+/// for _ in 0..2 {
+///     strfmt_write("hi {name:>4}!", &vars, &mut out).unwrap();
+///     out.write_char('\n').unwrap();
+/// }
+/// assert_eq!(out, "hi  bob!\nhi  bob!\n");
+/// ```
+pub fn strfmt_write<V: Vars + ?Sized, W: fmt::Write>(
+    fmtstr: &str,
+    vars: &V,
+    out: &mut W,
+) -> Result<()> {
+    let formatter = |mut fmt: Formatter| vars.display_var(&mut fmt);
+    strfmt_map_write(fmtstr, &formatter, out)
+}
+
+/// Rust-style format a string given a `HashMap` of the variables, appending
+/// the output to an existing `String`.
+/// see [strfmt] for details
+///
+/// On error `out` is left unchanged.
+pub fn strfmt_append<V: Vars + ?Sized>(fmtstr: &str, vars: &V, out: &mut String) -> Result<()> {
+    let len = out.len();
+    let result = strfmt_write(fmtstr, vars, out);
+    if result.is_err() {
+        out.truncate(len);
+    }
+    result
+}
+
+/// Rust-style format a string given a `HashMap` of the variables, writing
+/// the output to any `io::Write` such as a file or socket.
+/// see [strfmt] for details
+///
+/// The output is written in many small pieces, so `out` should be buffered
+/// (i.e. with `io::BufWriter`).
+///
+/// # Exceptions
+///
+/// * [FmtError::WriteError] - writing to `out` failed, with the io error message
+/// * any exception of [strfmt]
+#[cfg(feature = "std")]
+pub fn strfmt_io<V: Vars + ?Sized, W: io::Write>(
+    fmtstr: &str,
+    vars: &V,
+    out: &mut W,
+) -> Result<()> {
+    let mut out = IoWrite::new(out);
+    let result = strfmt_write(fmtstr, vars, &mut out);
+    out.finish(result)
+}
+
+/// Format a single value with a spec, without a template.
+///
+/// This is the same as formatting `{x:<spec>}` with only `x` in the variables.
+///
+/// # Exceptions
+///
+/// * [FmtError::Invalid] - The spec is structured incorrectly
+/// * [FmtError::TypeError] - The spec contains an unexpected option for the value
+///
+/// # Examples
+///
+/// ```
+/// use strfmt::format_value;
+///
+/// assert_eq!(format_value(">10.3e", &1234.5).unwrap(), "   1.234e3");
+/// assert_eq!(format_value("^7", &"hi").unwrap(), "  hi   ");
+/// ```
+pub fn format_value(spec: &str, value: &dyn DisplayStr) -> Result<String> {
+    let mut out = String::new();
+    format_value_write(spec, value, &mut out)?;
+    Ok(out)
+}
+
+/// Format a single value with a spec onto `out`.
+/// see [format_value] for details
+pub fn format_value_write(
+    spec: &str,
+    value: &dyn DisplayStr,
+    out: &mut dyn fmt::Write,
+) -> Result<()> {
+    let mut fmt = Formatter::from_parts("", "", spec, out);
+    value.display_str(&mut fmt)
+}
+
 /// Rust-style format a string given a `HashMap` of the variables.
 /// see [strfmt] for details
 #[deprecated(
     since = "0.2.0",
     note = "This function contains a bug when formatting numbers. Use strfmt instead"
 )]
-pub fn strfmt_display<'a, K, T: fmt::Display>(fmtstr: &str, vars: &HashMap<K, T>) -> Result<String>
+#[cfg(feature = "std")]
+pub fn strfmt_display<K, T: fmt::Display>(fmtstr: &str, vars: &HashMap<K, T>) -> Result<String>
 where
     K: Hash + Eq + FromStr,
 {
     let formatter = |mut fmt: Formatter| {
-        let k: K = match fmt.key.parse() {
-            Ok(k) => k,
-            Err(_) => {
-                return Err(new_key_error(fmt.key));
-            }
-        };
-        let v = match vars.get(&k) {
-            Some(v) => v,
-            None => {
-                return Err(new_key_error(fmt.key));
-            }
-        };
+        let v = get_var(&fmt, |k| vars.get(k))?;
         fmt.str(v.to_string().as_str())
     };
     strfmt_map(fmtstr, &formatter)
 }
 
+/// Rust-style format a string given a `HashMap` of the variables and a [Registry]
+/// of named presets, referenced as `{key:@name}`.
+/// see [strfmt] and [Registry] for details
+///
+/// # Exceptions
+///
+/// * [FmtError::KeyError] - `vars` or `registry` contains an invalid key
+/// * any exception of [strfmt]
+pub fn strfmt_registry<V: Vars + ?Sized>(
+    fmtstr: &str,
+    vars: &V,
+    registry: &Registry,
+) -> Result<String> {
+    // the value is looked up when it is displayed, with the preset's spec
+    let var = VarValue(vars);
+    let formatter = |fmt: Formatter| registry.display_with(fmt, |_| Ok(&var));
+    strfmt_map(fmtstr, &formatter)
+}
+
+/// the variable of [Vars] named by the key of the Formatter it is displayed with
+struct VarValue<'v, V: Vars + ?Sized>(&'v V);
+
+impl<'v, V: Vars + ?Sized> DisplayStr for VarValue<'v, V> {
+    fn display_str(&self, f: &mut Formatter) -> Result<()> {
+        self.0.display_var(f)
+    }
+}
+
 macro_rules! display_str_impl {
     ($($t:ident)*) => ($(
         impl DisplayStr for $t {
@@ -139,17 +340,100 @@ impl DisplayStr for Box<dyn DisplayStr> {
 
 /// This trait is effectively an re-implementation for [std::fmt::Display]
 /// It is used to disguise between the value types that should be formatted
+///
+/// A type can accept its own spec syntax (like python's `__format__`) by reading
+/// [Formatter::raw_spec]. The standard spec is only enforced by the builtin
+/// formatting methods such as [Formatter::str].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::fmt::Write;
+/// use strfmt::{strfmt, DisplayStr, Formatter, Result};
+///
+/// struct Ip([u8; 4]);
+///
+/// impl DisplayStr for Ip {
+///     fn display_str(&self, f: &mut Formatter) -> Result<()> {
+///         let [a, b, c, d] = self.0;
+///         match f.raw_spec() {
+///             "v6" => {
+///                 write!(f, "::ffff:{}.{}.{}.{}", a, b, c, d).unwrap();
+///                 Ok(())
+///             }
+///             _ => f.str(&format!("{}.{}.{}.{}", a, b, c, d)),
+///         }
+///     }
+/// }
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("ip".to_string(), Ip([127, 0, 0, 1]));
+/// assert_eq!(strfmt("{ip:v6}", &vars).unwrap(), "::ffff:127.0.0.1");
+/// assert_eq!(strfmt("{ip:>10}", &vars).unwrap(), " 127.0.0.1");
+/// ```
 pub trait DisplayStr {
     fn display_str(&self, f: &mut Formatter) -> Result<()>;
 }
 
+/// A source of variables for a template, looked up by the key of each field.
+///
+/// This is implemented for `HashMap` and `BTreeMap` with any key that can be parsed
+/// from the field's key, and for slices and `Vec`s indexed by the key.
+pub trait Vars {
+    /// format the variable named by `fmt.key` using `fmt`
+    ///
+    /// # Errors
+    /// * [FmtError::KeyError] - there is no variable named `fmt.key`
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl<K, T: DisplayStr, S: BuildHasher> Vars for HashMap<K, T, S>
+where
+    K: Hash + Eq + FromStr,
+{
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()> {
+        get_var(fmt, |k| self.get(k))?.display_str(fmt)
+    }
+}
+
+impl<K, T: DisplayStr> Vars for BTreeMap<K, T>
+where
+    K: Ord + FromStr,
+{
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()> {
+        get_var(fmt, |k| self.get(k))?.display_str(fmt)
+    }
+}
+
+/// the positional fields `0`, `1`, ... of a format string, i.e. the
+/// conversions of [Syntax::Percent] (`%s %5.2f`)
+impl<T: DisplayStr> Vars for [T] {
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()> {
+        get_var(fmt, |k: &usize| self.get(*k))?.display_str(fmt)
+    }
+}
+
+impl<T: DisplayStr> Vars for Vec<T> {
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()> {
+        self.as_slice().display_var(fmt)
+    }
+}
+
+impl<V: Vars + ?Sized> Vars for &V {
+    fn display_var(&self, fmt: &mut Formatter) -> Result<()> {
+        (**self).display_var(fmt)
+    }
+}
+
 /// This trait is a shortcut for [strfmt]
 /// for an example see [Format::format]
 pub trait Format {
     /// format a string using strfmt
     /// # Arguments
     ///
-    /// * `vars` - A `HashMap` holding the variables to use
+    /// * `vars` - A `HashMap`, `BTreeMap` or other [Vars] holding the variables to use
     ///
     /// # Errors
     /// Errors are passed directly from strfmt, for details see [strfmt]
@@ -167,9 +451,7 @@ pub trait Format {
     ///
     /// println!("{}", "|{Alpha}|{Beta:<5.2}|".format(&my_vars).unwrap());
     /// ```
-    fn format<K, D: DisplayStr>(&self, vars: &HashMap<K, D>) -> Result<String>
-    where
-        K: Hash + Eq + FromStr;
+    fn format<V: Vars + ?Sized>(&self, vars: &V) -> Result<String>;
 
     /// format a string using strfmt_display
     /// see [Format::format] for usage
@@ -177,19 +459,18 @@ pub trait Format {
         since = "0.2.0",
         note = "This function contains a bug when formatting numbers. Use format instead"
     )]
+    #[cfg(feature = "std")]
     fn format_display<K, D: fmt::Display>(&self, vars: &HashMap<K, D>) -> Result<String>
     where
         K: Hash + Eq + FromStr;
 }
 
 impl Format for String {
-    fn format<'a, K, D: DisplayStr>(&self, vars: &HashMap<K, D>) -> Result<String>
-    where
-        K: Hash + Eq + FromStr,
-    {
+    fn format<V: Vars + ?Sized>(&self, vars: &V) -> Result<String> {
         strfmt(self.as_str(), vars)
     }
-    fn format_display<'a, K, D: fmt::Display>(&self, vars: &HashMap<K, D>) -> Result<String>
+    #[cfg(feature = "std")]
+    fn format_display<K, D: fmt::Display>(&self, vars: &HashMap<K, D>) -> Result<String>
     where
         K: Hash + Eq + FromStr,
     {
@@ -199,13 +480,11 @@ impl Format for String {
 }
 
 impl Format for str {
-    fn format<K, D: DisplayStr>(&self, vars: &HashMap<K, D>) -> Result<String>
-    where
-        K: Hash + Eq + FromStr,
-    {
+    fn format<V: Vars + ?Sized>(&self, vars: &V) -> Result<String> {
         strfmt(self, vars)
     }
-    fn format_display<'a, K, D: fmt::Display>(&self, vars: &HashMap<K, D>) -> Result<String>
+    #[cfg(feature = "std")]
+    fn format_display<K, D: fmt::Display>(&self, vars: &HashMap<K, D>) -> Result<String>
     where
         K: Hash + Eq + FromStr,
     {
@@ -213,9 +492,30 @@ impl Format for str {
         strfmt_display(self, vars)
     }
 }
+/// parse the key of `fmt` and look it up with `get`
+fn get_var<'v, K, T, F>(fmt: &Formatter, get: F) -> Result<&'v T>
+where
+    K: FromStr,
+    F: FnOnce(&K) -> Option<&'v T>,
+{
+    let k: K = match fmt.key.parse() {
+        Ok(k) => k,
+        Err(_) => {
+            return Err(new_key_error(fmt));
+        }
+    };
+    match get(&k) {
+        Some(v) => Ok(v),
+        None => Err(new_key_error(fmt)),
+    }
+}
 
-fn new_key_error(key: &str) -> FmtError {
+fn new_key_error(fmt: &Formatter) -> FmtError {
+    // an invalid spec takes precedence over a missing key
+    if let Err(e) = fmt.check_spec() {
+        return e;
+    }
     let mut msg = String::new();
-    write!(msg, "Invalid key: {}", key).unwrap();
+    write!(msg, "Invalid key: {}", fmt.key).unwrap();
     FmtError::KeyError(msg)
 }
@@ -0,0 +1,126 @@
+use indent::Indent;
+use syntax::Syntax;
+use width::WidthMode;
+
+/// Options that apply to every field of a render, see
+/// [strfmt_with](crate::strfmt_with) and [Template::render_with](crate::Template::render_with).
+///
+/// # Examples
+///
+/// ```
+/// use strfmt::{Options, WidthMode};
+///
+/// let options = Options::new().with_width_mode(WidthMode::Chars);
+/// assert_eq!(options.width_mode(), WidthMode::Chars);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Options {
+    width_mode: WidthMode,
+    ansi: bool,
+    indent: Indent,
+    strip_styles: bool,
+    syntax: Syntax,
+    python: bool,
+}
+
+impl Options {
+    /// the default options, the same as rendering without options
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// measure strings with `mode`, unless the spec of a field sets its own
+    pub fn with_width_mode(mut self, mode: WidthMode) -> Options {
+        self.width_mode = mode;
+        self
+    }
+
+    /// width mode getter
+    pub fn width_mode(&self) -> WidthMode {
+        self.width_mode
+    }
+
+    /// treat ANSI escape sequences (i.e. colors) in values as zero width.
+    /// When precision truncates a value the sequences before the cut are kept
+    /// and a reset (`ESC[0m`) is written after it.
+    pub fn with_ansi(mut self, ansi: bool) -> Options {
+        self.ansi = ansi;
+        self
+    }
+
+    /// ansi getter
+    pub fn ansi(&self) -> bool {
+        self.ansi
+    }
+
+    /// indent the lines after the first of multi-line values with `indent`,
+    /// unless a field has its own `!indent` annotation
+    pub fn with_indent(mut self, indent: Indent) -> Options {
+        self.indent = indent;
+        self
+    }
+
+    /// indent getter
+    pub fn indent(&self) -> Indent {
+        self.indent
+    }
+
+    /// write the `!style=` annotations of fields as ANSI escape sequences,
+    /// the default. Turn this off when the output is not a terminal:
+    ///
+    /// ```
+    /// use std::io::IsTerminal;
+    /// use strfmt::Options;
+    ///
+    /// let options = Options::new().with_styles(std::io::stdout().is_terminal());
+    /// ```
+    pub fn with_styles(mut self, styles: bool) -> Options {
+        self.strip_styles = !styles;
+        self
+    }
+
+    /// styles getter
+    pub fn styles(&self) -> bool {
+        !self.strip_styles
+    }
+
+    /// read the fields of format strings in `syntax`. A [Template](crate::Template)
+    /// keeps the syntax it was parsed with, see [Template::parse_with](crate::Template::parse_with)
+    pub fn with_syntax(mut self, syntax: Syntax) -> Options {
+        self.syntax = syntax;
+        self
+    }
+
+    /// syntax getter
+    pub fn syntax(&self) -> Syntax {
+        self.syntax
+    }
+
+    /// format numbers exactly like python's `str.format` instead of rust's
+    /// `format!`: floats without a type are written like python's `repr`
+    /// (`1.0`, `1e+16`), `e`, `f` and `%` default to a precision of 6,
+    /// exponents have a sign and two digits (`1.000000e+02`), `#` keeps the
+    /// point and trailing zeros, a ` ` sign is written and the `0` flag and
+    /// `=` alignment pad after the sign. A negative integer in base `b`, `o`,
+    /// `x` or `X` is written as `-` and its magnitude (`-0x1f`) unless the
+    /// field has a bit width (`{x:#010x/32}`).
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use strfmt::{strfmt_with, Options};
+    ///
+    /// let mut vars = HashMap::new();
+    /// vars.insert("x".to_string(), 100.0);
+    /// let options = Options::new().with_python(true);
+    /// assert_eq!(strfmt_with("{x} {x:e} {x:+09.2f}", &vars, options).unwrap(), "100.0 1.000000e+02 +00100.00");
+    /// ```
+    pub fn with_python(mut self, python: bool) -> Options {
+        self.python = python;
+        self
+    }
+
+    /// python getter
+    pub fn python(&self) -> bool {
+        self.python
+    }
+}
@@ -0,0 +1,126 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+
+use formatter::Formatter;
+use spec::FormatSpec;
+use types::*;
+use DisplayStr;
+
+/// a custom formatter function registered in a [Registry]
+pub type CustomFormat = Box<dyn Fn(&mut Formatter, &dyn DisplayStr) -> Result<()> + Send + Sync>;
+
+enum Preset {
+    Spec(String),
+    Custom(CustomFormat),
+}
+
+/// A registry of named format presets.
+///
+/// A template refers to a preset by name with `{key:@name}`. A preset is either
+/// a standard spec string or a custom formatter function.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::fmt::Write;
+/// use strfmt::{strfmt_registry, Registry};
+///
+/// let mut registry = Registry::new();
+/// registry.add_spec("money", ">10.2").unwrap();
+/// registry.add_custom("quoted", |f, v| {
+///     f.write_str("\"").unwrap();
+///     v.display_str(f)?;
+///     f.write_str("\"").unwrap();
+///     Ok(())
+/// });
+///
+/// let mut vars: HashMap<String, f64> = HashMap::new();
+/// vars.insert("price".to_string(), 42.5);
+/// assert_eq!(
+///     strfmt_registry("{price:@money} {price:@quoted}", &vars, &registry).unwrap(),
+///     "     42.50 \"42.5\""
+/// );
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    presets: BTreeMap<String, Preset>,
+}
+
+impl Registry {
+    /// create an empty registry
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// register a preset using a standard spec string, i.e. `>12,.2f`
+    ///
+    /// # Errors
+    /// * [FmtError::Invalid] or [FmtError::TypeError] if `spec` is not a valid spec
+    pub fn add_spec(&mut self, name: &str, spec: &str) -> Result<()> {
+        spec.parse::<FormatSpec>()?;
+        self.presets
+            .insert(name.to_string(), Preset::Spec(spec.to_string()));
+        Ok(())
+    }
+
+    /// register a preset using a custom formatter function
+    pub fn add_custom<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&mut Formatter, &dyn DisplayStr) -> Result<()> + Send + Sync + 'static,
+    {
+        self.presets
+            .insert(name.to_string(), Preset::Custom(Box::new(f)));
+    }
+
+    /// return true if a preset is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.presets.contains_key(name)
+    }
+
+    /// format `value` using the preset named in the spec of `fmt`.
+    ///
+    /// If the spec does not start with `@` it is formatted with `value.display_str`
+    /// as normal. This can be used from the closure given to
+    /// [strfmt_map](crate::strfmt_map).
+    ///
+    /// # Errors
+    /// * [FmtError::KeyError] if no preset with the given name exists
+    pub fn display_str(&self, fmt: Formatter, value: &dyn DisplayStr) -> Result<()> {
+        self.display_with(fmt, |_| Ok(value))
+    }
+
+    /// resolve the preset before calling `get` to find the value, so that
+    /// the value is looked up with the preset's spec
+    pub(crate) fn display_with<'v, F>(&self, fmt: Formatter, get: F) -> Result<()>
+    where
+        F: FnOnce(&Formatter) -> Result<&'v dyn DisplayStr>,
+    {
+        let spec = fmt.raw_spec();
+        if !spec.starts_with('@') {
+            let value = get(&fmt)?;
+            let mut fmt = fmt;
+            return value.display_str(&mut fmt);
+        }
+        let name = &spec[1..];
+        match self.presets.get(name) {
+            Some(Preset::Spec(spec)) => {
+                let mut fmt = fmt.with_spec(spec.as_str());
+                get(&fmt)?.display_str(&mut fmt)
+            }
+            Some(Preset::Custom(f)) => {
+                // the custom formatter starts with an empty spec
+                let mut fmt = fmt.with_spec("");
+                let value = get(&fmt)?;
+                f(&mut fmt, value)
+            }
+            None => {
+                let mut msg = String::new();
+                write!(msg, "Invalid preset: {}", name).unwrap();
+                Err(FmtError::KeyError(msg))
+            }
+        }
+    }
+}
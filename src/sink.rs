@@ -0,0 +1,89 @@
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+use core::fmt;
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::io;
+
+use types::*;
+
+/// Adapts an `io::Write` to `fmt::Write`, keeping the io error so it
+/// can be reported instead of the opaque `fmt::Error`
+#[cfg(feature = "std")]
+pub(crate) struct IoWrite<'w, W: io::Write + 'w> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<'w, W: io::Write> IoWrite<'w, W> {
+    pub(crate) fn new(inner: &'w mut W) -> IoWrite<'w, W> {
+        IoWrite { inner, error: None }
+    }
+
+    /// replace a write error in `result` with the io error that caused it
+    pub(crate) fn finish<T>(self, result: Result<T>) -> Result<T> {
+        match (result, self.error) {
+            (Err(FmtError::WriteError(_)), Some(e)) => Err(FmtError::WriteError(e.to_string())),
+            (result, _) => result,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'w, W: io::Write> fmt::Write for IoWrite<'w, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.inner.write_all(s.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Some(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+/// Writes UTF-8 into a fixed byte slice, remembering if it ran out of space
+pub(crate) struct SliceWrite<'w> {
+    buf: &'w mut [u8],
+    len: usize,
+    overflow: bool,
+}
+
+impl<'w> SliceWrite<'w> {
+    pub(crate) fn new(buf: &'w mut [u8]) -> SliceWrite<'w> {
+        SliceWrite {
+            buf,
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// return the number of bytes written, replacing a write error in `result`
+    /// with [FmtError::BufferOverflow] if the buffer is full
+    pub(crate) fn finish(self, result: Result<()>) -> Result<usize> {
+        match result {
+            Ok(()) => Ok(self.len),
+            Err(FmtError::WriteError(_)) if self.overflow => {
+                let mut msg = String::new();
+                write!(msg, "output does not fit in {} bytes", self.buf.len()).unwrap();
+                Err(FmtError::BufferOverflow(msg))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<'w> fmt::Write for SliceWrite<'w> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            self.overflow = true;
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -0,0 +1,715 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::fmt::Write;
+use core::str;
+use core::str::FromStr;
+
+use fmtnum::NumberStyle;
+use types::*;
+use width::WidthMode;
+
+/// A parsed format spec, the part of `{key:spec}` after the `:`.
+///
+/// The spec follows python's
+/// [format specification mini-language](https://docs.python.org/3/library/string.html#formatspec):
+///
+/// ```text
+/// [[fill]align][sign][#][0][width][grouping][.precision[[cut]marker]][type][~[indent]]
+/// ```
+///
+/// As an extension, a string longer than the precision can be truncated with
+/// an ellipsis marker (`…` or `...`) that counts towards the precision, i.e.
+/// `{name:.10…}`. The optional cut is where the string is cut: `<` at the start,
+/// `^` in the middle or `>` at the end (the default).
+///
+/// A string can also be wrapped at word boundaries into lines of the width with
+/// a trailing `~`, i.e. `{help:<30~}`. Every line is filled and aligned, and the
+/// lines after the first are indented by the optional hanging indent (`~4`).
+///
+/// It can be parsed with [str::parse], built with [FormatSpec::builder] and
+/// written back to its canonical text with `Display`. The [WidthMode] can
+/// only be set with the builder and is not part of the text.
+///
+/// # Examples
+///
+/// ```
+/// use strfmt::{Alignment, FormatSpec};
+///
+/// let spec: FormatSpec = "*^+10.2e".parse().unwrap();
+/// assert_eq!(spec.fill(), '*');
+/// assert_eq!(spec.align(), Alignment::Center);
+/// assert_eq!(spec.width(), Some(10));
+///
+/// let built = FormatSpec::builder()
+///     .fill('*')
+///     .align(Alignment::Center)
+///     .sign(strfmt::Sign::Plus)
+///     .width(10)
+///     .precision(2)
+///     .ty('e')
+///     .build()
+///     .unwrap();
+/// assert_eq!(built, spec);
+/// assert_eq!(built.to_string(), "*^+10.2e");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub(crate) fill: Option<char>,
+    pub(crate) align: Alignment,
+    pub(crate) sign: Sign,
+    pub(crate) positive_zero: bool,
+    pub(crate) alternate: bool,
+    pub(crate) zero: bool,
+    pub(crate) width: Option<usize>,
+    pub(crate) grouping: Option<char>,
+    pub(crate) precision: Option<usize>,
+    pub(crate) ty: Option<char>,
+    pub(crate) bits: Option<u32>,
+    pub(crate) width_mode: Option<WidthMode>,
+    pub(crate) ellipsis: Option<(Cut, &'static str)>,
+    pub(crate) wrap: Option<usize>,
+    /// only set by other syntaxes, not part of the text
+    pub(crate) number: NumberStyle,
+}
+
+impl Default for FormatSpec {
+    fn default() -> FormatSpec {
+        FormatSpec {
+            fill: None,
+            align: Alignment::Unspecified,
+            sign: Sign::Unspecified,
+            positive_zero: false,
+            alternate: false,
+            zero: false,
+            width: None,
+            grouping: None,
+            precision: None,
+            ty: None,
+            bits: None,
+            width_mode: None,
+            ellipsis: None,
+            wrap: None,
+            number: NumberStyle::default(),
+        }
+    }
+}
+
+impl FormatSpec {
+    /// create an empty spec, equivalent to `{key}`
+    pub fn new() -> FormatSpec {
+        FormatSpec::default()
+    }
+
+    /// start building a spec
+    pub fn builder() -> FormatSpecBuilder {
+        FormatSpecBuilder {
+            spec: FormatSpec::default(),
+        }
+    }
+
+    /// fill getter, `'0'` if only the zero flag was given
+    pub fn fill(&self) -> char {
+        match self.fill {
+            Some(c) => c,
+            None if self.zero => '0',
+            None => ' ',
+        }
+    }
+
+    /// align getter, `Equal` if only the zero flag was given
+    pub fn align(&self) -> Alignment {
+        if self.align == Alignment::Unspecified && self.fill.is_none() && self.zero {
+            Alignment::Equal
+        } else {
+            self.align.clone()
+        }
+    }
+
+    /// sign getter
+    pub fn sign(&self) -> Sign {
+        self.sign.clone()
+    }
+
+    /// negative zero coercion (`z`) getter, if a float that rounds to
+    /// negative zero is written as a positive zero
+    pub fn positive_zero(&self) -> bool {
+        self.positive_zero
+    }
+
+    /// alternate (`#`) getter
+    pub fn alternate(&self) -> bool {
+        self.alternate
+    }
+
+    /// zero padding (`0`) getter
+    pub fn zero(&self) -> bool {
+        self.zero
+    }
+
+    /// width getter
+    pub fn width(&self) -> Option<usize> {
+        self.width
+    }
+
+    /// grouping getter, the thousands separator (`,` or `_`)
+    pub fn grouping(&self) -> Option<char> {
+        self.grouping
+    }
+
+    /// precision getter
+    pub fn precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    /// type getter
+    pub fn ty(&self) -> Option<char> {
+        self.ty
+    }
+
+    /// bit width getter, if a negative integer in base `b`, `o`, `x` or `X`
+    /// is written in two's complement of that many bits
+    pub fn bits(&self) -> Option<u32> {
+        self.bits
+    }
+
+    /// width mode getter, `None` to use the mode of the render
+    pub fn width_mode(&self) -> Option<WidthMode> {
+        self.width_mode
+    }
+
+    /// ellipsis getter, where to cut a string longer than the precision
+    /// and the marker to write in its place
+    pub fn ellipsis(&self) -> Option<(Cut, &'static str)> {
+        self.ellipsis
+    }
+
+    /// wrap getter, the hanging indent if a string is wrapped into lines of the width
+    pub fn wrap(&self) -> Option<usize> {
+        self.wrap
+    }
+
+    /// check the options that can be validated without knowing the type being formatted
+    fn validate(&self) -> Result<()> {
+        if let Some(ty) = self.ty {
+            if !is_type_element(ty) {
+                let mut msg = String::new();
+                write!(msg, "Invalid type specifier: {:?}", ty).unwrap();
+                return Err(FmtError::TypeError(msg));
+            }
+        }
+        match self.grouping {
+            None => {}
+            Some(',') => match self.ty {
+                /* These are allowed. See PEP 378.*/
+                None | Some('d') | Some('e') | Some('f') | Some('g') | Some('E') | Some('G')
+                | Some('%') | Some('F') => {}
+                Some(ty) => {
+                    let mut msg = String::new();
+                    write!(msg, "Invalid comma type: {}", ty).unwrap();
+                    return Err(FmtError::Invalid(msg));
+                }
+            },
+            Some('_') => match self.ty {
+                /* These are allowed. See PEP 515.*/
+                None | Some('d') | Some('e') | Some('f') | Some('g') | Some('E') | Some('G')
+                | Some('%') | Some('F') | Some('b') | Some('o') | Some('x') | Some('X') => {}
+                Some(ty) => {
+                    let mut msg = String::new();
+                    write!(msg, "Invalid underscore type: {}", ty).unwrap();
+                    return Err(FmtError::Invalid(msg));
+                }
+            },
+            Some(c) => {
+                let mut msg = String::new();
+                write!(msg, "Invalid grouping: {:?}", c).unwrap();
+                return Err(FmtError::Invalid(msg));
+            }
+        }
+        if self.positive_zero {
+            if let Some(ty) = self.ty.filter(|&ty| !"eEfFgG%".contains(ty)) {
+                let mut msg = String::new();
+                write!(
+                    msg,
+                    "Negative zero coercion (z) not allowed with type {:?}",
+                    ty
+                )
+                .unwrap();
+                return Err(FmtError::Invalid(msg));
+            }
+        }
+        if let Some(bits) = self.bits {
+            if bits == 0 || bits > 128 {
+                let mut msg = String::new();
+                write!(msg, "Invalid bit width: {}", bits).unwrap();
+                return Err(FmtError::Invalid(msg));
+            }
+            if !matches!(self.ty, Some('b') | Some('o') | Some('x') | Some('X')) {
+                return Err(FmtError::TypeError(
+                    "Bit width requires type 'b', 'o', 'x' or 'X'".to_string(),
+                ));
+            }
+            if self.sign != Sign::Unspecified {
+                return Err(FmtError::Invalid(
+                    "Sign not allowed with a bit width".to_string(),
+                ));
+            }
+        }
+        if self.wrap.is_some() {
+            if self.width.is_none() {
+                return Err(FmtError::Invalid("Wrapping requires a width".to_string()));
+            }
+            if self.precision.is_some() {
+                return Err(FmtError::Invalid(
+                    "Precision not allowed with wrapping".to_string(),
+                ));
+            }
+            if let Some(ty) = self.ty {
+                if ty != 's' {
+                    let mut msg = String::new();
+                    write!(msg, "Wrapping not allowed with type {:?}", ty).unwrap();
+                    return Err(FmtError::TypeError(msg));
+                }
+            }
+        }
+        if let Some((_, marker)) = self.ellipsis {
+            match self.precision {
+                None => {
+                    return Err(FmtError::Invalid(
+                        "Truncation marker requires a precision".to_string(),
+                    ))
+                }
+                Some(precision) if precision < marker.chars().count() => {
+                    return Err(FmtError::Invalid(
+                        "Precision shorter than the truncation marker".to_string(),
+                    ))
+                }
+                Some(_) => {}
+            }
+            if let Some(ty) = self.ty {
+                if ty != 's' {
+                    let mut msg = String::new();
+                    write!(msg, "Truncation marker not allowed with type {:?}", ty).unwrap();
+                    return Err(FmtError::TypeError(msg));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FormatSpec {
+    type Err = FmtError;
+
+    fn from_str(s: &str) -> Result<FormatSpec> {
+        parse_like_python(s)
+    }
+}
+
+impl fmt::Display for FormatSpec {
+    /// write the canonical spec text, which parses back to an equal spec
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let align = match self.align {
+            Alignment::Unspecified => None,
+            Alignment::Left => Some('<'),
+            Alignment::Center => Some('^'),
+            Alignment::Right => Some('>'),
+            Alignment::Equal => Some('='),
+        };
+        if let Some(align) = align {
+            if let Some(fill) = self.fill {
+                f.write_char(fill)?;
+            }
+            f.write_char(align)?;
+        }
+        match self.sign {
+            Sign::Unspecified => {}
+            Sign::Plus => f.write_char('+')?,
+            Sign::Minus => f.write_char('-')?,
+            Sign::Space => f.write_char(' ')?,
+        }
+        if self.positive_zero {
+            f.write_char('z')?;
+        }
+        if self.alternate {
+            f.write_char('#')?;
+        }
+        if self.zero && self.fill.is_none() {
+            f.write_char('0')?;
+        }
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+        }
+        if let Some(grouping) = self.grouping {
+            f.write_char(grouping)?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+        if let Some((cut, marker)) = self.ellipsis {
+            match cut {
+                Cut::Start => f.write_char('<')?,
+                Cut::Middle => f.write_char('^')?,
+                Cut::End => {}
+            }
+            f.write_str(marker)?;
+        }
+        if let Some(ty) = self.ty {
+            f.write_char(ty)?;
+        }
+        if let Some(bits) = self.bits {
+            write!(f, "/{}", bits)?;
+        }
+        match self.wrap {
+            None => {}
+            Some(0) => f.write_char('~')?,
+            Some(indent) => write!(f, "~{}", indent)?,
+        }
+        Ok(())
+    }
+}
+
+/// Builder for a [FormatSpec], see [FormatSpec::builder]
+#[derive(Debug, Clone)]
+pub struct FormatSpecBuilder {
+    spec: FormatSpec,
+}
+
+impl FormatSpecBuilder {
+    /// set the fill character, only used if an alignment is also set
+    pub fn fill(mut self, fill: char) -> FormatSpecBuilder {
+        self.spec.fill = Some(fill);
+        self
+    }
+
+    /// set the alignment
+    pub fn align(mut self, align: Alignment) -> FormatSpecBuilder {
+        self.spec.align = align;
+        self
+    }
+
+    /// set the sign
+    pub fn sign(mut self, sign: Sign) -> FormatSpecBuilder {
+        self.spec.sign = sign;
+        self
+    }
+
+    /// set negative zero coercion (`z`), only allowed for floats
+    pub fn positive_zero(mut self, positive_zero: bool) -> FormatSpecBuilder {
+        self.spec.positive_zero = positive_zero;
+        self
+    }
+
+    /// set the alternate form (`#`)
+    pub fn alternate(mut self, alternate: bool) -> FormatSpecBuilder {
+        self.spec.alternate = alternate;
+        self
+    }
+
+    /// set zero padding (`0`), ignored if a fill is set
+    pub fn zero(mut self, zero: bool) -> FormatSpecBuilder {
+        self.spec.zero = zero;
+        self
+    }
+
+    /// set the width
+    pub fn width(mut self, width: usize) -> FormatSpecBuilder {
+        self.spec.width = Some(width);
+        self
+    }
+
+    /// set the thousands separator (`,` or `_`)
+    pub fn grouping(mut self, grouping: char) -> FormatSpecBuilder {
+        self.spec.grouping = Some(grouping);
+        self
+    }
+
+    /// set the precision
+    pub fn precision(mut self, precision: usize) -> FormatSpecBuilder {
+        self.spec.precision = Some(precision);
+        self
+    }
+
+    /// set the type
+    pub fn ty(mut self, ty: char) -> FormatSpecBuilder {
+        self.spec.ty = Some(ty);
+        self
+    }
+
+    /// write a negative integer in two's complement of `bits` bits,
+    /// only allowed for the types `b`, `o`, `x` and `X`
+    pub fn bits(mut self, bits: u32) -> FormatSpecBuilder {
+        self.spec.bits = Some(bits);
+        self
+    }
+
+    /// set how the width and precision of strings are measured,
+    /// overriding the mode of the render
+    pub fn width_mode(mut self, mode: WidthMode) -> FormatSpecBuilder {
+        self.spec.width_mode = Some(mode);
+        self
+    }
+
+    /// truncate a string longer than the precision by cutting it at `cut`
+    /// and writing `marker` in its place. The marker counts towards the precision.
+    pub fn ellipsis(mut self, cut: Cut, marker: &'static str) -> FormatSpecBuilder {
+        self.spec.ellipsis = Some((cut, marker));
+        self
+    }
+
+    /// wrap a string at word boundaries into lines of the width, indenting
+    /// the lines after the first by `indent` spaces
+    pub fn wrap(mut self, indent: usize) -> FormatSpecBuilder {
+        self.spec.wrap = Some(indent);
+        self
+    }
+
+    /// validate and return the spec. The zero flag is cleared if a fill is
+    /// set, like when parsing, as the fill replaces it.
+    ///
+    /// # Errors
+    /// The same errors as parsing the spec, i.e. [FmtError::TypeError] for
+    /// an unknown type, and [FmtError::Invalid] for a fill without an
+    /// alignment, which can't be written as spec text
+    pub fn build(mut self) -> Result<FormatSpec> {
+        if self.spec.fill.is_some() {
+            if self.spec.align == Alignment::Unspecified {
+                return Err(FmtError::Invalid("Fill requires an alignment".to_string()));
+            }
+            self.spec.zero = false;
+        }
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+}
+
+fn is_alignment_token(c: char) -> bool {
+    matches!(c, '=' | '<' | '^' | '>')
+}
+
+fn is_sign_element(c: char) -> bool {
+    matches!(c, ' ' | '-' | '+')
+}
+
+fn is_type_element(c: char) -> bool {
+    matches!(
+        c,
+        'b' | 'o' | 'x' | 'X' | 'c' | 'd' | 'n' | 'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%' | 's'
+    )
+}
+
+// get an integer from pos, returning the number of bytes
+// consumed and the integer
+fn get_integer(s: &[u8], pos: usize) -> (usize, Option<usize>) {
+    let (_, rest) = s.split_at(pos);
+    let mut consumed: usize = 0;
+    for b in rest {
+        match *b as char {
+            '0'..='9' => {}
+            _ => break,
+        };
+        consumed += 1;
+    }
+    if consumed == 0 {
+        (0, None)
+    } else {
+        let (intstr, _) = rest.split_at(consumed);
+        let val = unsafe {
+            // I think I can be reasonably sure that 0-9 chars are utf8 :)
+            str::from_utf8_unchecked(intstr).parse::<usize>().ok()
+        };
+        (consumed, val)
+    }
+}
+
+pub(crate) fn parse_like_python(rest: &str) -> Result<FormatSpec> {
+    // The rest of this was pretty much strait up copied from python's format parser
+    // All credit goes to python source file: formatter_unicode.c
+    //
+
+    let mut format = FormatSpec::default();
+    let mut chars = rest.chars();
+    let fake_fill = match chars.next() {
+        Some(c) => c,
+        None => return Ok(format),
+    };
+    // from now on all format characters MUST be valid
+    // ASCII characters (fill and identifier were the
+    // only ones that weren't.
+    // Therefore we can use bytes for the rest
+    let rest = rest.as_bytes();
+    let mut fill_specified = false;
+
+    let mut end: usize = rest.len();
+    let mut pos: usize = 0;
+
+    // If the second char is an alignment token,
+    // then fake_fill as fill
+    if end - pos > fake_fill.len_utf8()
+        && is_alignment_token(rest[pos + fake_fill.len_utf8()] as char)
+    {
+        format.align = parse_alignment(rest[pos + fake_fill.len_utf8()] as char);
+        format.fill = Some(fake_fill);
+        fill_specified = true;
+        pos += 1 + fake_fill.len_utf8();
+    } else if end - pos >= 1 && is_alignment_token(fake_fill) {
+        format.align = parse_alignment(fake_fill);
+        pos += fake_fill.len_utf8();
+    }
+
+    // Parse the various sign options
+    if end - pos >= 1 && is_sign_element(rest[pos] as char) {
+        format.sign = match rest[pos] {
+            b'+' => Sign::Plus,
+            b'-' => Sign::Minus,
+            _ => Sign::Space,
+        };
+        pos += 1;
+    }
+
+    // `z` coerces negative zero floats to positive zero
+    if end - pos >= 1 && rest[pos] == b'z' {
+        format.positive_zero = true;
+        pos += 1;
+    }
+
+    // If the next character is #, we're in alternate mode.  This only
+    // applies to integers.
+    if end - pos >= 1 && rest[pos] as char == '#' {
+        format.alternate = true;
+        pos += 1;
+    }
+
+    // The special case for 0-padding (backwards compat)
+    if !fill_specified && end - pos >= 1 && rest[pos] == b'0' {
+        format.zero = true;
+        pos += 1;
+    }
+
+    // check to make sure that val is good
+    let (consumed, val) = get_integer(rest, pos);
+    pos += consumed;
+    if consumed != 0 {
+        match val {
+            None => {
+                return Err(FmtError::Invalid(
+                    "overflow error when parsing width".to_string(),
+                ))
+            }
+            Some(v) => {
+                format.width = Some(v);
+            }
+        }
+    }
+
+    // Comma or underscore signifies add thousands separators
+    if end - pos > 0 && (rest[pos] as char == ',' || rest[pos] as char == '_') {
+        format.grouping = Some(rest[pos] as char);
+        pos += 1;
+    }
+
+    // Parse field precision
+    if end - pos > 0 && rest[pos] as char == '.' {
+        pos += 1;
+
+        let (consumed, val) = get_integer(rest, pos);
+        if consumed != 0 {
+            match val {
+                None => {
+                    return Err(FmtError::Invalid(
+                        "overflow error when parsing precision".to_string(),
+                    ))
+                }
+                Some(v) => {
+                    format.precision = Some(v);
+                }
+            }
+        } else {
+            // Not having a precision after a dot is an error.
+            if consumed == 0 {
+                return Err(FmtError::Invalid(
+                    "Format specifier missing precision".to_string(),
+                ));
+            }
+        }
+        pos += consumed;
+
+        // An ellipsis marker, optionally after where to cut the string
+        let cut = match rest.get(pos) {
+            Some(b'<') => Some(Cut::Start),
+            Some(b'^') => Some(Cut::Middle),
+            Some(b'>') => Some(Cut::End),
+            _ => None,
+        };
+        let marker_pos = if cut.is_some() { pos + 1 } else { pos };
+        let marker = ["\u{2026}", "..."]
+            .iter()
+            .find(|m| rest[marker_pos..].starts_with(m.as_bytes()));
+        if let Some(marker) = marker {
+            format.ellipsis = Some((cut.unwrap_or(Cut::End), marker));
+            pos = marker_pos + marker.len();
+        }
+    }
+
+    // Wrapping with an optional hanging indent ends the spec
+    if let Some(i) = rest[pos..].iter().position(|&b| b == b'~') {
+        let tilde = pos + i;
+        let (consumed, val) = get_integer(rest, tilde + 1);
+        if tilde + 1 + consumed != end {
+            return Err(FmtError::Invalid("Invalid format specifier".to_string()));
+        }
+        match val {
+            None if consumed != 0 => {
+                return Err(FmtError::Invalid(
+                    "overflow error when parsing indent".to_string(),
+                ))
+            }
+            indent => format.wrap = Some(indent.unwrap_or(0)),
+        }
+        end = tilde;
+    }
+
+    // A bit width for two's complement follows the type
+    if let Some(i) = rest[pos..end].iter().position(|&b| b == b'/') {
+        let slash = pos + i;
+        let (consumed, val) = get_integer(rest, slash + 1);
+        if consumed == 0 || slash + 1 + consumed != end {
+            return Err(FmtError::Invalid("Invalid format specifier".to_string()));
+        }
+        match val.filter(|&v| v <= u32::MAX as usize) {
+            None => {
+                return Err(FmtError::Invalid(
+                    "overflow error when parsing bit width".to_string(),
+                ))
+            }
+            bits => format.bits = bits.map(|b| b as u32),
+        }
+        end = slash;
+    }
+
+    // Finally, parse the type field.
+    if end - pos > 1 {
+        // More than one char remain, invalid format specifier.
+        return Err(FmtError::Invalid("Invalid format specifier".to_string()));
+    }
+
+    if end - pos == 1 {
+        format.ty = Some(rest[pos] as char);
+        // pos+=1;
+    }
+
+    // Do as much validating as we can, just by looking at the format
+    // specifier.  Do not take into account what type of formatting
+    // we're doing (int, float, string).
+    format.validate()?;
+    Ok(format)
+}
+
+fn parse_alignment(c: char) -> Alignment {
+    match c {
+        '<' => Alignment::Left,
+        '^' => Alignment::Center,
+        '>' => Alignment::Right,
+        '=' => Alignment::Equal,
+        _ => unreachable!(),
+    }
+}
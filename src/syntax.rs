@@ -0,0 +1,799 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+
+use fmtnum::NumberStyle;
+use fmtstr::{scan, Run, Token};
+use formatter::{parse_spec, split_pattern, Formatter};
+use spec::FormatSpec;
+use types::*;
+
+/// The syntax of the fields in a format string.
+///
+/// Every syntax has the same keys, `!` annotations and (except for
+/// [Syntax::Percent]) the same specs, only the delimiters and escapes differ.
+/// Set it for a render with [Options::with_syntax](crate::Options::with_syntax)
+/// or for a template with [Template::parse_with](crate::Template::parse_with).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use strfmt::{strfmt_with, Options, Syntax};
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("name".to_string(), "bob");
+/// let options = Options::new().with_syntax(Syntax::Dollar);
+/// assert_eq!(
+///     strfmt_with("{\"user\": \"${name:>5}\"}", &vars, options).unwrap(),
+///     "{\"user\": \"  bob\"}"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// `{name:spec}`, with `{{` and `}}` for literal braces. This is the default.
+    #[default]
+    Braces,
+    /// `${name:spec}`, with `$$` for a literal `$`. Braces and a `$` not
+    /// followed by `{` are literal text.
+    Dollar,
+    /// `{{name:spec}}`, with `\{{` for a literal `{{`. Single braces are
+    /// literal text and spaces around the field are ignored.
+    Mustache,
+    /// C's `printf` and python's `%` conversions: `%-10s`, `%5.2f`, `%08x`
+    /// or named `%(name)s`, with `%%` for a literal `%`.
+    ///
+    /// The flags, width, precision and conversion are translated to a spec,
+    /// with `-` aligning left and right alignment otherwise. Positional
+    /// conversions have the keys `0`, `1`, ... in order, and a `*` width or
+    /// precision is read from the next positional key before the value.
+    /// Length modifiers (`%ld`) are ignored.
+    Percent,
+    /// .NET composite formatting: `{0}`, `{0,-10}` or `{0,10:N2}`, with `{{`
+    /// and `}}` for literal braces.
+    ///
+    /// A negative alignment aligns left, a positive one right. The standard
+    /// numeric formats are written like the `en-US` culture: `C` (currency),
+    /// `D` (digits), `E`, `F`, `G`, `N` (grouped), `P` (percent), `R` and `X`
+    /// (hex digits), with an optional precision. Exponents have a sign and
+    /// three digits for `E` (`1.5E+003`) and two for `G` and `R` (`1.5E+03`).
+    /// `D` and `X` are only for integers. Custom formats (`0.00`) are not
+    /// supported.
+    DotNet,
+    /// rust's `std::fmt`: `{}`, `{0}` or `{name:>8.2}`, with `{{` and `}}`
+    /// for literal braces.
+    ///
+    /// Fields without a key have the keys `0`, `1`, ... in order. The width
+    /// and precision can be read from an argument with `{:>1$}`,
+    /// `{:.prec$}` or `{:.*}` (which reads the precision from the next
+    /// positional key before the value). The `0` flag pads numbers with
+    /// zeros after the sign, `?` is the debug format and `x?`/`X?` the
+    /// debug hex. Integers in the `x`, `o` and `b` bases are written in two's
+    /// complement like `format!`.
+    Rust,
+}
+
+impl Syntax {
+    /// the text around the pattern of a field
+    pub(crate) fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            Syntax::Braces | Syntax::DotNet | Syntax::Rust => ("{", "}"),
+            Syntax::Dollar => ("${", "}"),
+            Syntax::Mustache => ("{{", "}}"),
+            Syntax::Percent => ("%", ""),
+        }
+    }
+
+    /// read a format string, calling `f` with every run of literal text and
+    /// the pattern of every field
+    pub(crate) fn scan<'s, F>(self, fmtstr: &'s str, f: F) -> Result<()>
+    where
+        F: FnMut(Token<'s>) -> Result<()>,
+    {
+        let mut f = f;
+        match self {
+            Syntax::Braces | Syntax::DotNet | Syntax::Rust => scan(fmtstr, f),
+            Syntax::Dollar => scan_dollar(fmtstr, &mut f),
+            Syntax::Mustache => scan_mustache(fmtstr, &mut f),
+            Syntax::Percent => scan_percent(fmtstr, &mut f),
+        }
+    }
+
+    /// split a pattern into its identifier, its spec and the keys of its
+    /// `*` arguments. Positional `%` and rust fields are numbered with `positions`.
+    pub(crate) fn split_field<'p>(
+        self,
+        pattern: &'p str,
+        positions: &mut Positions,
+    ) -> Result<(Cow<'p, str>, &'p str, StarArgs)> {
+        match self {
+            Syntax::Percent => {}
+            Syntax::Rust => return Ok(split_rust_field(pattern, positions)),
+            Syntax::DotNet => {
+                let end = pattern.find([',', ':']).unwrap_or(pattern.len());
+                if end == 0 {
+                    return Err(FmtError::Invalid("must specify identifier".to_string()));
+                }
+                let (identifier, raw_spec) = pattern.split_at(end);
+                return Ok((
+                    Cow::Borrowed(identifier.trim_end()),
+                    raw_spec,
+                    StarArgs::default(),
+                ));
+            }
+            _ => {
+                let (identifier, raw_spec) = split_pattern(pattern)?;
+                return Ok((Cow::Borrowed(identifier), raw_spec, StarArgs::default()));
+            }
+        }
+        let (identifier, raw_spec) = match pattern.strip_prefix('(') {
+            Some(rest) => match rest.find(')') {
+                Some(0) => return Err(FmtError::Invalid("must specify identifier".to_string())),
+                Some(i) => (Some(&rest[..i]), &rest[i + 1..]),
+                None => return Err(expected(")")),
+            },
+            None => (None, pattern),
+        };
+        // the `*` arguments come before the value
+        let (width, precision) = match raw_spec.find('.') {
+            Some(dot) => (&raw_spec[..dot], &raw_spec[dot + 1..]),
+            None => (raw_spec, ""),
+        };
+        let mut stars = StarArgs::default();
+        if width.contains('*') {
+            stars.width = Some(positions.next_key());
+        }
+        if precision.starts_with('*') {
+            stars.precision = Some(positions.next_key());
+        }
+        let identifier = match identifier {
+            Some(identifier) => Cow::Borrowed(identifier),
+            None => Cow::Owned(positions.next_key()),
+        };
+        Ok((identifier, raw_spec, stars))
+    }
+
+    /// parse the spec of a pattern, keeping the error instead of failing
+    pub(crate) fn parse_spec(self, raw_spec: &str) -> (FormatSpec, Option<FmtError>) {
+        match self {
+            Syntax::Percent => match parse_percent(raw_spec) {
+                Ok(spec) => (spec, None),
+                Err(e) => (FormatSpec::default(), Some(e)),
+            },
+            Syntax::DotNet => match parse_dotnet(raw_spec) {
+                Ok(spec) => (spec, None),
+                Err(e) => (FormatSpec::default(), Some(e)),
+            },
+            Syntax::Rust => match parse_rust(raw_spec) {
+                Ok(spec) => (spec, None),
+                Err(e) => (FormatSpec::default(), Some(e)),
+            },
+            _ => parse_spec(raw_spec),
+        }
+    }
+}
+
+fn expected(close: &str) -> FmtError {
+    let mut msg = String::new();
+    write!(msg, "Expected '{}' before end of string", close).unwrap();
+    FmtError::Invalid(msg)
+}
+
+fn scan_dollar<'s, F>(fmtstr: &'s str, f: &mut F) -> Result<()>
+where
+    F: FnMut(Token<'s>) -> Result<()>,
+{
+    let bytes = fmtstr.as_bytes();
+    let mut run = Run::new();
+    let mut i = 0;
+    while let Some(p) = fmtstr[i..].find('$') {
+        let dollar = i + p;
+        run.push(fmtstr, i, dollar, f)?;
+        match bytes.get(dollar + 1) {
+            Some(&b'$') => {
+                run.push(fmtstr, dollar, dollar + 1, f)?;
+                i = dollar + 2;
+            }
+            Some(&b'{') => {
+                let end = match fmtstr[dollar + 2..].find('}') {
+                    Some(e) => dollar + 2 + e,
+                    None => return Err(expected("}")),
+                };
+                run.flush(fmtstr, f)?;
+                f(Token::Field(&fmtstr[dollar + 2..end]))?;
+                i = end + 1;
+            }
+            _ => {
+                run.push(fmtstr, dollar, dollar + 1, f)?;
+                i = dollar + 1;
+            }
+        }
+    }
+    run.push(fmtstr, i, fmtstr.len(), f)?;
+    run.flush(fmtstr, f)
+}
+
+fn scan_mustache<'s, F>(fmtstr: &'s str, f: &mut F) -> Result<()>
+where
+    F: FnMut(Token<'s>) -> Result<()>,
+{
+    let mut run = Run::new();
+    let mut i = 0;
+    while let Some(p) = fmtstr[i..].find("{{") {
+        let open = i + p;
+        if fmtstr[..open].ends_with('\\') {
+            run.push(fmtstr, i, open - 1, f)?;
+            run.push(fmtstr, open, open + 2, f)?;
+            i = open + 2;
+            continue;
+        }
+        run.push(fmtstr, i, open, f)?;
+        let end = match fmtstr[open + 2..].find("}}") {
+            Some(e) => open + 2 + e,
+            None => return Err(expected("}}")),
+        };
+        run.flush(fmtstr, f)?;
+        f(Token::Field(fmtstr[open + 2..end].trim()))?;
+        i = end + 2;
+    }
+    run.push(fmtstr, i, fmtstr.len(), f)?;
+    run.flush(fmtstr, f)
+}
+
+fn scan_percent<'s, F>(fmtstr: &'s str, f: &mut F) -> Result<()>
+where
+    F: FnMut(Token<'s>) -> Result<()>,
+{
+    let bytes = fmtstr.as_bytes();
+    let mut run = Run::new();
+    let mut i = 0;
+    while let Some(p) = fmtstr[i..].find('%') {
+        let percent = i + p;
+        run.push(fmtstr, i, percent, f)?;
+        if bytes.get(percent + 1) == Some(&b'%') {
+            run.push(fmtstr, percent, percent + 1, f)?;
+            i = percent + 2;
+            continue;
+        }
+        let mut end = percent + 1;
+        if bytes.get(end) == Some(&b'(') {
+            end = match fmtstr[end..].find(')') {
+                Some(c) => end + c + 1,
+                None => return Err(expected(")")),
+            };
+        }
+        // the flags, width, precision and length, then the conversion
+        while end < bytes.len() && b"-+ #0123456789.*hlLqjzt".contains(&bytes[end]) {
+            end += 1;
+        }
+        match fmtstr[end..].chars().next() {
+            Some(c) if c.is_ascii_alphabetic() => end += 1,
+            _ => {
+                return Err(FmtError::Invalid(
+                    "Expected a conversion type after '%'".to_string(),
+                ))
+            }
+        }
+        run.flush(fmtstr, f)?;
+        f(Token::Field(&fmtstr[percent + 1..end]))?;
+        i = end;
+    }
+    run.push(fmtstr, i, fmtstr.len(), f)?;
+    run.flush(fmtstr, f)
+}
+
+/// gives the positional fields of a format string the keys `0`, `1`, ...
+pub(crate) struct Positions(usize);
+
+impl Positions {
+    pub(crate) fn new() -> Positions {
+        Positions(0)
+    }
+
+    fn next_key(&mut self) -> String {
+        let key = self.0.to_string();
+        self.0 += 1;
+        key
+    }
+}
+
+/// the keys of the arguments a `*` width and precision are read from
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct StarArgs {
+    width: Option<String>,
+    precision: Option<String>,
+}
+
+impl StarArgs {
+    /// if the spec has no `*` width or precision
+    pub(crate) fn is_empty(&self) -> bool {
+        self.width.is_none() && self.precision.is_none()
+    }
+
+    /// the keys, in the order they are read
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        self.width
+            .iter()
+            .chain(self.precision.iter())
+            .map(|k| k.as_str())
+    }
+
+    /// set the width and precision of `spec` from the `*` arguments, read
+    /// by rendering them with `f`
+    pub(crate) fn apply<F>(&self, spec: &mut FormatSpec, f: &mut F) -> Result<()>
+    where
+        F: FnMut(Formatter) -> Result<()>,
+    {
+        if let Some(ref key) = self.width {
+            let width = star_arg(key, f)?;
+            // a negative width aligns left, like the `-` flag
+            if width < 0 {
+                spec.align = Alignment::Left;
+            }
+            spec.width = Some(width.unsigned_abs() as usize);
+        }
+        if let Some(ref key) = self.precision {
+            // a negative precision is taken as if it were omitted
+            let precision = star_arg(key, f)?;
+            if precision >= 0 {
+                spec.precision = Some(precision as usize);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// render the `*` argument `key` with `f` and read it as an integer
+fn star_arg<F>(key: &str, f: &mut F) -> Result<i64>
+where
+    F: FnMut(Formatter) -> Result<()>,
+{
+    let mut value = String::new();
+    f(Formatter::new(key, FormatSpec::default(), &mut value))?;
+    value.parse().map_err(|_| {
+        let mut msg = String::new();
+        write!(msg, "* wants an integer, got {:?} for key {}", value, key).unwrap();
+        FmtError::TypeError(msg)
+    })
+}
+
+/// translate the flags, width, precision and conversion of a `%` field
+fn parse_percent(raw_spec: &str) -> Result<FormatSpec> {
+    let mut builder = FormatSpec::builder();
+    let mut align = Alignment::Right;
+    let mut zero = false;
+    let mut rest = raw_spec;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '-' => align = Alignment::Left,
+            '+' => builder = builder.sign(Sign::Plus),
+            ' ' => builder = builder.sign(Sign::Space),
+            '#' => builder = builder.alternate(true),
+            '0' => zero = true,
+            _ => break,
+        }
+        rest = &rest[1..];
+    }
+    builder = builder.align(align.clone());
+    // a `*` width or precision is set from its argument when rendering
+    if let Some(after) = rest.strip_prefix('*') {
+        rest = after;
+    }
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    if digits > 0 {
+        builder = builder.width(rest[..digits].parse().map_err(|_| invalid(raw_spec))?);
+        rest = &rest[digits..];
+    }
+    if let Some(after) = rest.strip_prefix(".*") {
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix('.') {
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        // `%.s` is a precision of zero
+        builder = builder.precision(after[..digits].parse().unwrap_or(0));
+        rest = &after[digits..];
+    }
+    // the length modifiers of C are not needed
+    rest = rest.trim_start_matches(|c| "hlLqjzt".contains(c));
+    builder = match rest {
+        "s" | "r" | "a" | "d" | "i" | "u" => builder,
+        "F" => builder.ty('f'),
+        "c" | "f" | "e" | "E" | "g" | "G" | "x" | "X" | "o" => {
+            builder.ty(rest.chars().next().unwrap())
+        }
+        _ => {
+            let mut msg = String::new();
+            write!(msg, "Unknown conversion type {:?}", rest).unwrap();
+            return Err(FmtError::TypeError(msg));
+        }
+    };
+    let mut spec = builder.build()?;
+    // numbers are padded with zeros after the sign, unless aligned left
+    spec.number.zero_pad = zero && align == Alignment::Right;
+    // like C, `%f` and `%e` have 6 digits after the point by default
+    if spec.precision.is_none() && matches!(spec.ty, Some('f' | 'e' | 'E')) {
+        spec.precision = Some(6);
+    }
+    Ok(spec)
+}
+
+/// translate the `,alignment:format` of a .NET field
+fn parse_dotnet(raw_spec: &str) -> Result<FormatSpec> {
+    let mut builder = FormatSpec::builder();
+    let (alignment, format) = match raw_spec.find(':') {
+        Some(colon) => (&raw_spec[..colon], &raw_spec[colon + 1..]),
+        None => (raw_spec, ""),
+    };
+    if let Some(alignment) = alignment.strip_prefix(',') {
+        let alignment: i64 = alignment.trim().parse().map_err(|_| invalid(raw_spec))?;
+        builder = builder
+            .align(if alignment < 0 {
+                Alignment::Left
+            } else {
+                Alignment::Right
+            })
+            .width(alignment.unsigned_abs() as usize);
+    }
+    let mut chars = format.chars();
+    let letter = match chars.next() {
+        Some(c) => c,
+        None => return builder.build(),
+    };
+    let digits = chars.as_str();
+    let precision = match digits {
+        "" => None,
+        _ if digits.len() <= 2 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(digits.parse().unwrap())
+        }
+        _ => letter_error(format)?,
+    };
+    let mut number = NumberStyle::default();
+    builder = match letter {
+        'C' | 'c' => {
+            number.prefix = "$";
+            builder
+                .grouping(',')
+                .ty('f')
+                .precision(precision.unwrap_or(2))
+        }
+        // only integers, floats are an error like in .NET
+        'D' | 'd' => {
+            number.min_digits = precision.unwrap_or(0);
+            builder.ty('d')
+        }
+        'E' | 'e' => {
+            number.exp_digits = 3;
+            builder.ty(letter).precision(precision.unwrap_or(6))
+        }
+        'F' | 'f' => builder.ty('f').precision(precision.unwrap_or(2)),
+        'G' | 'g' => {
+            number.exp_digits = 2;
+            let upper = letter == 'G';
+            match precision {
+                // the shortest text that reads back as the same value
+                None | Some(0) => {
+                    number.shortest_exp = Some(if upper { 'E' } else { 'e' });
+                    builder
+                }
+                Some(p) => builder.ty(if upper { 'G' } else { 'g' }).precision(p),
+            }
+        }
+        'R' | 'r' if precision.is_none() => {
+            number.exp_digits = 2;
+            number.shortest_exp = Some(if letter == 'R' { 'E' } else { 'e' });
+            builder
+        }
+        'N' | 'n' => builder
+            .grouping(',')
+            .ty('f')
+            .precision(precision.unwrap_or(2)),
+        'P' | 'p' => builder
+            .grouping(',')
+            .ty('%')
+            .precision(precision.unwrap_or(2)),
+        'X' | 'x' => {
+            number.min_digits = precision.unwrap_or(0);
+            builder.ty(letter)
+        }
+        _ => letter_error(format)?,
+    };
+    let mut spec = builder.build()?;
+    spec.number = number;
+    Ok(spec)
+}
+
+/// a width or precision of a rust spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Count<'s> {
+    None,
+    Is(usize),
+    /// `name$` or `1$`
+    Arg(&'s str),
+    /// `.*`
+    Star,
+}
+
+/// the parts of a rust spec, `[[fill]align][sign]['#']['0'][width]['.' precision]type`
+#[derive(Debug)]
+struct RustSpec<'s> {
+    fill: Option<char>,
+    align: Alignment,
+    sign: Sign,
+    alternate: bool,
+    zero: bool,
+    width: Count<'s>,
+    precision: Count<'s>,
+    ty: &'s str,
+}
+
+fn rust_alignment(c: char) -> Option<Alignment> {
+    match c {
+        '<' => Some(Alignment::Left),
+        '^' => Some(Alignment::Center),
+        '>' => Some(Alignment::Right),
+        _ => None,
+    }
+}
+
+/// read an integer or an argument followed by `$` from the start of `s`
+fn rust_count(s: &str) -> Result<(Count<'_>, &str)> {
+    let end = s
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    if end > 0 && s[end..].starts_with('$') {
+        return Ok((Count::Arg(&s[..end]), &s[end + 1..]));
+    }
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if digits == 0 {
+        return Ok((Count::None, s));
+    }
+    let n = s[..digits].parse().map_err(|_| invalid(s))?;
+    Ok((Count::Is(n), &s[digits..]))
+}
+
+fn split_rust(raw_spec: &str) -> Result<RustSpec<'_>> {
+    let mut spec = RustSpec {
+        fill: None,
+        align: Alignment::Unspecified,
+        sign: Sign::Unspecified,
+        alternate: false,
+        zero: false,
+        width: Count::None,
+        precision: Count::None,
+        ty: "",
+    };
+    let mut rest = raw_spec;
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next().and_then(rust_alignment)) {
+        (Some(fill), Some(align)) => {
+            spec.fill = Some(fill);
+            spec.align = align;
+            rest = &rest[fill.len_utf8() + 1..];
+        }
+        (Some(c), None) => {
+            if let Some(align) = rust_alignment(c) {
+                spec.align = align;
+                rest = &rest[1..];
+            }
+        }
+        (None, _) => {}
+    }
+    if let Some(after) = rest.strip_prefix('+') {
+        spec.sign = Sign::Plus;
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix('-') {
+        spec.sign = Sign::Minus;
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix('#') {
+        spec.alternate = true;
+        rest = after;
+    }
+    // `0$` is a width argument, not the flag
+    if rest.starts_with('0') && !rest.starts_with("0$") {
+        spec.zero = true;
+        rest = &rest[1..];
+    }
+    let (width, after) = rust_count(rest)?;
+    spec.width = width;
+    rest = after;
+    if let Some(after) = rest.strip_prefix(".*") {
+        spec.precision = Count::Star;
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix('.') {
+        let (precision, after) = rust_count(after)?;
+        if precision == Count::None {
+            return Err(FmtError::Invalid(
+                "Format specifier missing precision".to_string(),
+            ));
+        }
+        spec.precision = precision;
+        rest = after;
+    }
+    spec.ty = rest;
+    Ok(spec)
+}
+
+/// split a rust pattern, numbering the fields without a key and `.*`
+fn split_rust_field<'p>(
+    pattern: &'p str,
+    positions: &mut Positions,
+) -> (Cow<'p, str>, &'p str, StarArgs) {
+    let (identifier, raw_spec) = match pattern.find(':') {
+        Some(colon) => (&pattern[..colon], &pattern[colon + 1..]),
+        None => (pattern, ""),
+    };
+    // an invalid spec is reported when the field is formatted
+    let mut stars = StarArgs::default();
+    if let Ok(spec) = split_rust(raw_spec) {
+        if let Count::Arg(key) = spec.width {
+            stars.width = Some(key.to_string());
+        }
+        stars.precision = match spec.precision {
+            Count::Arg(key) => Some(key.to_string()),
+            Count::Star => Some(positions.next_key()),
+            _ => None,
+        };
+    }
+    let identifier = if identifier.is_empty() || identifier.starts_with('!') {
+        Cow::Owned(positions.next_key() + identifier)
+    } else {
+        Cow::Borrowed(identifier)
+    };
+    (identifier, raw_spec, stars)
+}
+
+/// translate a rust spec, the arguments of the width and precision are
+/// set when rendering
+fn parse_rust(raw_spec: &str) -> Result<FormatSpec> {
+    let rust = split_rust(raw_spec)?;
+    let mut builder = FormatSpec::builder().align(rust.align).sign(rust.sign);
+    if let Some(fill) = rust.fill {
+        builder = builder.fill(fill);
+    }
+    builder = builder.alternate(rust.alternate);
+    if let Count::Is(width) = rust.width {
+        builder = builder.width(width);
+    }
+    if let Count::Is(precision) = rust.precision {
+        builder = builder.precision(precision);
+    }
+    builder = match rust.ty {
+        "" | "?" => builder,
+        "x?" => builder.ty('x'),
+        "X?" => builder.ty('X'),
+        "x" | "X" | "o" | "b" | "e" | "E" => builder.ty(rust.ty.chars().next().unwrap()),
+        _ => {
+            let mut msg = String::new();
+            write!(msg, "Unknown format trait {:?}", rust.ty).unwrap();
+            return Err(FmtError::TypeError(msg));
+        }
+    };
+    let mut spec = builder.build()?;
+    spec.number.zero_pad = rust.zero;
+    // debug is a type of the rust syntax only, the other syntaxes reject it
+    if rust.ty == "?" {
+        spec.ty = Some('?');
+    }
+    Ok(spec)
+}
+
+fn letter_error<T>(format: &str) -> Result<T> {
+    let mut msg = String::new();
+    write!(msg, "Unsupported format string: {:?}", format).unwrap();
+    Err(FmtError::TypeError(msg))
+}
+
+fn invalid(raw_spec: &str) -> FmtError {
+    let mut msg = String::new();
+    write!(msg, "Invalid format specifier: {}", raw_spec).unwrap();
+    FmtError::Invalid(msg)
+}
+
+#[test]
+fn test_scan_syntax() {
+    fn tokens(syntax: Syntax, fmtstr: &str) -> Result<String> {
+        let mut out = String::new();
+        syntax.scan(fmtstr, |token| {
+            match token {
+                Token::Literal(s) => write!(out, "<{}>", s).unwrap(),
+                Token::Field(p) => write!(out, "[{}]", p).unwrap(),
+            }
+            Ok(())
+        })?;
+        Ok(out)
+    }
+    assert_eq!(
+        tokens(Syntax::Dollar, "{\"a\": ${a:>3}, $$5 $x}$").unwrap(),
+        "<{\"a\": >[a:>3]<, $><5 $x}$>"
+    );
+    assert!(tokens(Syntax::Dollar, "${a").is_err());
+    assert_eq!(
+        tokens(Syntax::Mustache, "{a} {{ b:^4 }}\\{{c}}{{d}}").unwrap(),
+        "<{a} >[b:^4]<{{c}}>[d]"
+    );
+    assert!(tokens(Syntax::Mustache, "{{a}").is_err());
+    assert_eq!(
+        tokens(Syntax::Percent, "100%% %(a)s {b} %(c)-+08.3f!").unwrap(),
+        "<100%>< >[(a)s]< {b} >[(c)-+08.3f]<!>"
+    );
+    assert_eq!(
+        tokens(Syntax::Percent, "%s%-*.*ld%c").unwrap(),
+        "[s][-*.*ld][c]"
+    );
+    assert!(tokens(Syntax::Percent, "%(a)").is_err());
+    assert!(tokens(Syntax::Percent, "%-5").is_err());
+    assert!(tokens(Syntax::Percent, "%(a").is_err());
+    assert!(tokens(Syntax::Percent, "50%").is_err());
+}
+
+#[test]
+fn test_parse_percent() {
+    let spec = |s: &str| parse_percent(s).map(|spec| spec.to_string());
+    assert_eq!(spec("s").unwrap(), ">");
+    assert_eq!(spec("-10s").unwrap(), "<10");
+    assert_eq!(spec("+.2f").unwrap(), ">+.2f");
+    assert_eq!(spec("#x").unwrap(), ">#x");
+    assert_eq!(spec(".s").unwrap(), ">.0");
+    assert_eq!(spec("05d").unwrap(), ">5");
+    assert!(parse_percent("05d").unwrap().number.zero_pad);
+    assert_eq!(spec("-05d").unwrap(), "<5");
+    assert!(!parse_percent("-05d").unwrap().number.zero_pad);
+    assert_eq!(spec("*.*d").unwrap(), ">");
+    assert_eq!(spec("lu").unwrap(), ">");
+    assert_eq!(spec("5c").unwrap(), ">5c");
+    assert_eq!(spec("F").unwrap(), ">.6f");
+    assert_eq!(spec("e").unwrap(), ">.6e");
+    assert_eq!(spec(".*e").unwrap(), ">.6e");
+    assert_eq!(spec(".3G").unwrap(), ">.3G");
+    assert!(spec("n").is_err());
+    assert!(spec("5").is_err());
+}
+
+#[test]
+fn test_split_field() {
+    use alloc::vec::Vec;
+    let split = |s: &str| {
+        let mut positions = Positions::new();
+        let (identifier, raw_spec, stars) = Syntax::Percent.split_field(s, &mut positions)?;
+        let stars: Vec<&str> = stars.keys().collect();
+        Ok::<_, FmtError>((
+            identifier.into_owned(),
+            raw_spec.to_string(),
+            stars.join(","),
+        ))
+    };
+    assert_eq!(
+        split("(a)-5s").unwrap(),
+        ("a".into(), "-5s".into(), "".into())
+    );
+    assert_eq!(split("5d").unwrap(), ("0".into(), "5d".into(), "".into()));
+    assert_eq!(
+        split("*.*f").unwrap(),
+        ("2".into(), "*.*f".into(), "0,1".into())
+    );
+    assert_eq!(
+        split(".*s").unwrap(),
+        ("1".into(), ".*s".into(), "0".into())
+    );
+    assert!(split("()s").is_err());
+    let mut positions = Positions::new();
+    let (a, _, _) = Syntax::Percent.split_field("s", &mut positions).unwrap();
+    let (b, _, _) = Syntax::Percent.split_field("(x)s", &mut positions).unwrap();
+    let (c, _, _) = Syntax::Percent.split_field("s", &mut positions).unwrap();
+    assert_eq!((&*a, &*b, &*c), ("0", "x", "1"));
+    let (d, _, _) = Syntax::Braces.split_field("k:>4", &mut positions).unwrap();
+    assert_eq!(d, "k");
+    let mut positions = Positions::new();
+    let (e, raw_spec, stars) = Syntax::Rust.split_field(":>w$.*", &mut positions).unwrap();
+    let stars: Vec<&str> = stars.keys().collect();
+    assert_eq!(
+        (&*e, raw_spec, stars.join(",")),
+        ("1", ">w$.*", "w,0".into())
+    );
+    let (f, _, _) = Syntax::Rust.split_field("!indent", &mut positions).unwrap();
+    assert_eq!(f, "2!indent");
+}
@@ -0,0 +1,575 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::cmp;
+use core::fmt;
+use core::str::FromStr;
+
+use ansi;
+use fmtstr::Token;
+use formatter::Formatter;
+use indent::{split_annotations, Annotations, Line};
+use options::Options;
+use sink::SliceWrite;
+use spec::FormatSpec;
+use syntax::{Positions, StarArgs, Syntax};
+use translate::{self, LossyField, Translation};
+use types::*;
+use Vars;
+
+/// A format string that has been parsed once so it can be rendered many times.
+///
+/// The syntax is the same as [strfmt](crate::strfmt()). Errors in the structure of
+/// the string are reported by [Template::parse], errors in a spec when the
+/// field is rendered (so that a custom [DisplayStr](crate::DisplayStr) can
+/// use its own spec).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use strfmt::Template;
+///
+/// let template = Template::parse("{name:>5}: {score:.1}").unwrap();
+///
+/// let mut vars: HashMap<String, f64> = HashMap::new();
+/// vars.insert("name".to_string(), 1.0);
+/// vars.insert("score".to_string(), 9.25);
+/// assert_eq!(template.render(&vars).unwrap(), "    1: 9.2");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Literal(String),
+    Field(Box<Field>),
+}
+
+/// a `{key:spec}` in a template, with the spec already parsed
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    pattern: String,
+    key: String,
+    annotations: Annotations,
+    spec_start: usize,
+    spec: FormatSpec,
+    spec_error: Option<FmtError>,
+    syntax: Syntax,
+    stars: StarArgs,
+}
+
+impl Field {
+    fn new(syntax: Syntax, pattern: &str, positions: &mut Positions) -> Result<Field> {
+        let (identifier, raw_spec, stars) = syntax.split_field(pattern, positions)?;
+        let (key, annotations) = split_annotations(&identifier)?;
+        let (spec, spec_error) = syntax.parse_spec(raw_spec);
+        Ok(Field {
+            pattern: pattern.to_string(),
+            key: key.to_string(),
+            annotations,
+            spec_start: pattern.len() - raw_spec.len(),
+            spec,
+            spec_error,
+            syntax,
+            stars,
+        })
+    }
+
+    /// the field as it is written in the format string
+    fn text(&self) -> String {
+        let (open, close) = self.syntax.delimiters();
+        let mut text = String::new();
+        text.push_str(open);
+        text.push_str(&self.pattern);
+        text.push_str(close);
+        text
+    }
+
+    /// the first part of the field that is lost when `text` is read back
+    /// as a field with `key` in `syntax`
+    fn loss(&self, text: &str, key: &str, syntax: Syntax) -> Option<&'static str> {
+        if self.spec_error.is_some() {
+            return Some("the invalid spec");
+        }
+        if self.stars.keys().next().is_some() {
+            return Some("the width or precision argument");
+        }
+        let template = match Template::parse_with(text, syntax) {
+            Ok(template) => template,
+            Err(_) => return Some("the key"),
+        };
+        let read = match template.pieces.as_slice() {
+            [Piece::Field(ref read)] => read,
+            _ => return Some("the key"),
+        };
+        if read.key != key {
+            Some("the key")
+        } else if read.annotations != self.annotations {
+            Some("the annotations")
+        } else if read.spec_error.is_some() {
+            Some("the spec")
+        } else {
+            translate::spec_loss(&self.spec, &read.spec)
+        }
+    }
+
+    fn formatter<'a, 'b>(
+        &'a self,
+        spec: FormatSpec,
+        line: Line<'a>,
+        out: &'b mut dyn fmt::Write,
+    ) -> Formatter<'a, 'b> {
+        let mut fmt = Formatter::from_parsed(
+            &self.key,
+            &self.pattern,
+            &self.pattern[self.spec_start..],
+            spec,
+            self.spec_error.clone(),
+            out,
+        );
+        fmt.set_annotations(self.annotations);
+        fmt.set_syntax(self.syntax);
+        fmt.set_line(line);
+        fmt
+    }
+}
+
+impl Template {
+    /// parse a format string
+    ///
+    /// # Errors
+    /// * [FmtError::Invalid] - The format string is structured incorrectly
+    pub fn parse(fmtstr: &str) -> Result<Template> {
+        Template::parse_with(fmtstr, Syntax::Braces)
+    }
+
+    /// parse a format string with the fields in `syntax`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use strfmt::{Syntax, Template};
+    ///
+    /// let template = Template::parse_with("%(name)-5s|%(n)4.1f", Syntax::Percent).unwrap();
+    /// let mut vars: HashMap<String, f64> = HashMap::new();
+    /// vars.insert("name".to_string(), 1.0);
+    /// vars.insert("n".to_string(), 2.25);
+    /// assert_eq!(template.render(&vars).unwrap(), "1    | 2.2");
+    /// ```
+    ///
+    /// # Errors
+    /// * [FmtError::Invalid] - The format string is structured incorrectly
+    pub fn parse_with(fmtstr: &str, syntax: Syntax) -> Result<Template> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut positions = Positions::new();
+        syntax.scan(fmtstr, |token| {
+            match token {
+                Token::Literal(s) => literal.push_str(s),
+                Token::Field(pattern) => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal.clone()));
+                        literal.clear();
+                    }
+                    pieces.push(Piece::Field(Box::new(Field::new(
+                        syntax,
+                        pattern,
+                        &mut positions,
+                    )?)));
+                }
+            }
+            Ok(())
+        })?;
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template { pieces })
+    }
+
+    /// the keys of the fields, in the order they appear. The keys of the `*`
+    /// width and precision of a [Syntax::Percent] field come before its key.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys = Vec::new();
+        for piece in &self.pieces {
+            if let Piece::Field(ref field) = *piece {
+                keys.extend(field.stars.keys());
+                keys.push(&field.key);
+            }
+        }
+        keys
+    }
+
+    /// return true if the template has no fields, so it always renders to the
+    /// same text. `{{` and `}}` escapes are allowed.
+    pub fn is_static(&self) -> bool {
+        self.pieces.iter().all(|piece| match *piece {
+            Piece::Literal(_) => true,
+            Piece::Field(_) => false,
+        })
+    }
+
+    /// write the template in another syntax, listing the fields that can't
+    /// be written exactly. See [Translation] for an example.
+    ///
+    /// A field is lossy if it would not render the same in `syntax`: i.e.
+    /// [Syntax::Percent] has no center alignment or fill, and braces have
+    /// no `*` width. Positional [Syntax::Percent] fields keep their keys
+    /// (`{0}`, `{1}`) and fields with the next positional key are written
+    /// as positional conversions.
+    pub fn translate(&self, syntax: Syntax) -> Translation {
+        let mut translation = Translation::new();
+        let mut positional = 0;
+        for piece in &self.pieces {
+            match *piece {
+                Piece::Literal(ref s) => translate::write_literal(syntax, s, &mut translation.text),
+                Piece::Field(ref field) => {
+                    let text = &mut translation.text;
+                    let separated = translate::field_can_follow(syntax, text);
+                    let start = text.len();
+                    let key = translate::write_field(
+                        syntax,
+                        &field.key,
+                        field.annotations,
+                        &field.spec,
+                        &mut positional,
+                        text,
+                    );
+                    let loss = if separated {
+                        field.loss(&text[start..], key, syntax)
+                    } else {
+                        Some("the text before the field")
+                    };
+                    if let Some(reason) = loss {
+                        translation.lossy.push(LossyField {
+                            field: field.text(),
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+        translation
+    }
+
+    /// render the template given the variables
+    ///
+    /// # Errors
+    /// * [FmtError::KeyError] - `vars` contains an invalid key
+    /// * [FmtError::TypeError] - the given format code for a field contains an unexpected option
+    pub fn render<V: Vars + ?Sized>(&self, vars: &V) -> Result<String> {
+        let mut out = String::new();
+        self.render_write(vars, &mut out)?;
+        Ok(out)
+    }
+
+    /// render the template given the variables, borrowing the text of the
+    /// template instead of copying it if it [is static](Template::is_static)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use std::collections::HashMap;
+    /// use strfmt::Template;
+    ///
+    /// let vars: HashMap<String, String> = HashMap::new();
+    /// let template = Template::parse("no fields {{here}}").unwrap();
+    /// assert!(template.is_static());
+    /// match template.render_cow(&vars).unwrap() {
+    ///     Cow::Borrowed(s) => assert_eq!(s, "no fields {here}"),
+    ///     Cow::Owned(_) => panic!(),
+    /// }
+    /// ```
+    pub fn render_cow<V: Vars + ?Sized>(&self, vars: &V) -> Result<Cow<'_, str>> {
+        match self.pieces.as_slice() {
+            [] => Ok(Cow::Borrowed("")),
+            [Piece::Literal(ref s)] => Ok(Cow::Borrowed(s.as_str())),
+            _ => self.render(vars).map(Cow::Owned),
+        }
+    }
+
+    /// render the template onto any `fmt::Write`, see [Template::render]
+    pub fn render_write<V: Vars + ?Sized, W: fmt::Write>(
+        &self,
+        vars: &V,
+        out: &mut W,
+    ) -> Result<()> {
+        self.render_map_write(|mut fmt: Formatter| vars.display_var(&mut fmt), out)
+    }
+
+    /// render the template given the variables and [Options] for the whole render
+    pub fn render_with<V: Vars + ?Sized>(&self, vars: &V, options: Options) -> Result<String> {
+        let mut out = String::new();
+        self.render_write_with(vars, options, &mut out)?;
+        Ok(out)
+    }
+
+    /// render the template onto any `fmt::Write` with [Options] for the whole render
+    pub fn render_write_with<V: Vars + ?Sized, W: fmt::Write>(
+        &self,
+        vars: &V,
+        options: Options,
+        out: &mut W,
+    ) -> Result<()> {
+        let f = |mut fmt: Formatter| vars.display_var(&mut fmt);
+        self.render_map_write_with(options, f, out)
+    }
+
+    /// render the template once for every row of variables, see [Template::render]
+    ///
+    /// As an iterator each row is a new `String`, allocated with the length of
+    /// the longest row so far. [RenderEach::next_str] instead renders every row
+    /// into one reused buffer, and [Template::render_each_write] writes all the
+    /// rows onto one sink.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use strfmt::Template;
+    ///
+    /// let rows: Vec<HashMap<String, i32>> = (1..4)
+    ///     .map(|i| vec![("n".to_string(), i)].into_iter().collect())
+    ///     .collect();
+    ///
+    /// let template = Template::parse("n={n:>2}").unwrap();
+    /// let lines: Result<Vec<String>, _> = template.render_each(&rows).collect();
+    /// assert_eq!(lines.unwrap(), vec!["n= 1", "n= 2", "n= 3"]);
+    /// ```
+    pub fn render_each<I>(&self, rows: I) -> RenderEach<'_, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: Vars,
+    {
+        RenderEach {
+            template: self,
+            rows: rows.into_iter(),
+            capacity: 0,
+            buffer: String::new(),
+        }
+    }
+
+    /// render the template once for every row of variables onto one `fmt::Write`,
+    /// writing `separator` between the rows. Returns the number of rows written.
+    ///
+    /// Rendering stops at the first error, so on error `out` may contain
+    /// part of the output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use strfmt::Template;
+    ///
+    /// let mut row = BTreeMap::new();
+    /// row.insert("a".to_string(), 1.5);
+    /// let rows = vec![row.clone(), row];
+    ///
+    /// let mut csv = String::new();
+    /// let template = Template::parse("{a},{a:.2}").unwrap();
+    /// assert_eq!(template.render_each_write(&rows, "\n", &mut csv).unwrap(), 2);
+    /// assert_eq!(csv, "1.5,1.50\n1.5,1.50");
+    /// ```
+    pub fn render_each_write<I, W>(&self, rows: I, separator: &str, out: &mut W) -> Result<usize>
+    where
+        I: IntoIterator,
+        I::Item: Vars,
+        W: fmt::Write,
+    {
+        let mut count = 0;
+        for vars in rows {
+            if count > 0 {
+                out.write_str(separator)?;
+            }
+            self.render_write(&vars, out)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// UNSTABLE: the Formatter object is still considered unstable
+    ///
+    /// render the template given a closure that uses a Formatter,
+    /// see [strfmt_map](crate::strfmt_map)
+    pub fn render_map<F>(&self, f: F) -> Result<String>
+    where
+        F: FnMut(Formatter) -> Result<()>,
+    {
+        let mut out = String::new();
+        self.render_map_write(f, &mut out)?;
+        Ok(out)
+    }
+
+    /// UNSTABLE: the Formatter object is still considered unstable
+    ///
+    /// render the template onto any `fmt::Write` given a closure that uses a Formatter
+    pub fn render_map_write<F, W>(&self, f: F, out: &mut W) -> Result<()>
+    where
+        F: FnMut(Formatter) -> Result<()>,
+        W: fmt::Write,
+    {
+        self.render_map_write_with(Options::default(), f, out)
+    }
+
+    /// [Template::render_map_write] with the formatters created with `options`
+    fn render_map_write_with<F, W>(&self, options: Options, f: F, out: &mut W) -> Result<()>
+    where
+        F: FnMut(Formatter) -> Result<()>,
+        W: fmt::Write,
+    {
+        let mut f = f;
+        let out: &mut dyn fmt::Write = out;
+        let mut line = Line::new();
+        for piece in &self.pieces {
+            match *piece {
+                Piece::Literal(ref s) => {
+                    line.literal(s);
+                    out.write_str(s)?;
+                }
+                Piece::Field(ref field) => {
+                    let mut spec = field.spec.clone();
+                    field.stars.apply(&mut spec, &mut f)?;
+                    let mut fmt = field.formatter(spec, line, out);
+                    fmt.set_options(options);
+                    let style_started = Cell::new(false);
+                    fmt.start_style_with(&style_started);
+                    f(fmt)?;
+                    if style_started.get() {
+                        out.write_str(ansi::RESET)?;
+                    }
+                    line.field();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator rendering a template for every row of variables,
+/// created by [Template::render_each]
+#[derive(Debug)]
+pub struct RenderEach<'t, I> {
+    template: &'t Template,
+    rows: I,
+    capacity: usize,
+    /// the buffer reused by [RenderEach::next_str]
+    buffer: String,
+}
+
+impl<'t, I> RenderEach<'t, I>
+where
+    I: Iterator,
+    I::Item: Vars,
+{
+    /// render the next row into a buffer that is reused for every row,
+    /// instead of allocating a `String` like [Iterator::next]. The row is
+    /// borrowed until the next call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use strfmt::Template;
+    ///
+    /// let rows: Vec<HashMap<String, i32>> = (1..4)
+    ///     .map(|i| vec![("n".to_string(), i)].into_iter().collect())
+    ///     .collect();
+    ///
+    /// let template = Template::parse("n={n}").unwrap();
+    /// let mut each = template.render_each(&rows);
+    /// let mut total = 0;
+    /// while let Some(row) = each.next_str() {
+    ///     total += row.unwrap().len();
+    /// }
+    /// assert_eq!(total, 9);
+    /// ```
+    pub fn next_str(&mut self) -> Option<Result<&str>> {
+        let vars = self.rows.next()?;
+        self.buffer.clear();
+        match self.template.render_write(&vars, &mut self.buffer) {
+            Ok(()) => Some(Ok(self.buffer.as_str())),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'t, I> Iterator for RenderEach<'t, I>
+where
+    I: Iterator,
+    I::Item: Vars,
+{
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        let vars = self.rows.next()?;
+        let mut out = String::with_capacity(self.capacity);
+        let result = self.template.render_write(&vars, &mut out);
+        self.capacity = cmp::max(self.capacity, out.len());
+        Some(result.map(|_| out))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl FromStr for Template {
+    type Err = FmtError;
+
+    fn from_str(s: &str) -> Result<Template> {
+        Template::parse(s)
+    }
+}
+
+/// Render a template into a caller provided buffer instead of a `String`,
+/// returning the number of bytes written.
+///
+/// The output is UTF-8, so `&buf[..len]` can be read with `str::from_utf8`.
+///
+/// The output is never allocated, but rendering is not free of allocation:
+/// the key of every field is parsed into the key type of a map (a `String`
+/// for `HashMap<String, _>`), a number longer than 128 bytes (i.e. a float
+/// with a large precision) uses a temporary `String`, and errors carry
+/// an allocated message. A [Vars](crate::Vars) with keys that parse
+/// without allocating, like a slice of positional values or a map with
+/// integer keys, avoids the key allocation.
+///
+/// # Errors
+/// * [FmtError::BufferOverflow] - the output does not fit in `buf`
+/// * any error of [Template::render]
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use strfmt::{render_into_slice, FmtError, Template};
+///
+/// let template = Template::parse("cpu={cpu:.1}%").unwrap();
+/// let mut vars: HashMap<String, f32> = HashMap::new();
+/// vars.insert("cpu".to_string(), 42.17);
+///
+/// let mut buf = [0u8; 16];
+/// let len = render_into_slice(&template, &vars, &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"cpu=42.2%");
+///
+/// let mut small = [0u8; 4];
+/// match render_into_slice(&template, &vars, &mut small) {
+///     Err(FmtError::BufferOverflow(_)) => {}
+///     _ => panic!(),
+/// }
+/// ```
+pub fn render_into_slice<V: Vars + ?Sized>(
+    template: &Template,
+    vars: &V,
+    buf: &mut [u8],
+) -> Result<usize> {
+    let mut out = SliceWrite::new(buf);
+    let result = template.render_write(vars, &mut out);
+    out.finish(result)
+}
@@ -0,0 +1,71 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars(s: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("s".to_string(), s.to_string());
+    vars
+}
+
+fn ansi() -> Options {
+    Options::new().with_ansi(true)
+}
+
+#[test]
+fn test_ansi_width() {
+    let red = vars("\x1b[31mred\x1b[0m");
+    // the escape sequences are counted by default
+    assert_eq!(strfmt("[{s:>8}]", &red).unwrap(), "[\x1b[31mred\x1b[0m]");
+    assert_eq!(
+        strfmt_with("[{s:>8}]", &red, ansi()).unwrap(),
+        "[     \x1b[31mred\x1b[0m]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:*^7}]", &red, ansi()).unwrap(),
+        "[**\x1b[31mred\x1b[0m**]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:<5}]", &vars("a\x1b[1mb"), ansi()).unwrap(),
+        "[a\x1b[1mb   ]"
+    );
+}
+
+#[test]
+fn test_ansi_truncate() {
+    let colored = vars("\x1b[1;32mgreen\x1b[0m and plain");
+    // the sequence is kept and a reset is added after the cut
+    assert_eq!(
+        strfmt_with("[{s:.3}]", &colored, ansi()).unwrap(),
+        "[\x1b[1;32mgre\x1b[0m]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:<5.3}]", &colored, ansi()).unwrap(),
+        "[\x1b[1;32mgre\x1b[0m  ]"
+    );
+    // the sequences right after the cut are kept
+    assert_eq!(
+        strfmt_with("[{s:.5}]", &colored, ansi()).unwrap(),
+        "[\x1b[1;32mgreen\x1b[0m\x1b[0m]"
+    );
+    // no sequence, no reset
+    assert_eq!(
+        strfmt_with("[{s:.2}]", &vars("plain"), ansi()).unwrap(),
+        "[pl]"
+    );
+    // not truncated, no reset
+    assert_eq!(
+        strfmt_with("[{s:.20}]", &colored, ansi()).unwrap(),
+        "[\x1b[1;32mgreen\x1b[0m and plain]"
+    );
+    // a cut before the first sequence
+    assert_eq!(
+        strfmt_with("[{s:.1}]", &vars("ab\x1b[31mc"), ansi()).unwrap(),
+        "[a]"
+    );
+
+    let template = Template::parse("{s:.3}|").unwrap();
+    assert_eq!(
+        template.render_with(&colored, ansi()).unwrap(),
+        "\x1b[1;32mgre\x1b[0m|"
+    );
+}
@@ -0,0 +1,106 @@
+use super::super::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+fn vars() -> HashMap<String, i64> {
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), 1);
+    vars.insert("y".to_string(), 2);
+    vars
+}
+
+#[test]
+fn test_cache() {
+    let cache = TemplateCache::new(2);
+    let vars = vars();
+    assert!(cache.is_empty());
+    assert_eq!(cache.strfmt("{x}", &vars).unwrap(), "1");
+    assert_eq!(cache.strfmt("{x}", &vars).unwrap(), "1");
+    assert_eq!(cache.strfmt("{y:>3}", &vars).unwrap(), "  2");
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+    assert_eq!(cache.len(), 2);
+
+    // the same template is shared
+    assert!(Arc::ptr_eq(
+        &cache.get("{x}").unwrap(),
+        &cache.get("{x}").unwrap()
+    ));
+
+    // "{y:>3}" is the least recently used
+    cache.get("{x}-{y}").unwrap();
+    assert_eq!(cache.len(), 2);
+    let misses = cache.misses();
+    cache.get("{x}").unwrap();
+    assert_eq!(cache.misses(), misses);
+    cache.get("{y:>3}").unwrap();
+    assert_eq!(cache.misses(), misses + 1);
+
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.capacity(), 2);
+}
+
+#[test]
+fn test_cache_lru() {
+    let cache = TemplateCache::new(3);
+    for fmtstr in ["{x}", "{y}", "{x}{y}", "{y}", "{x}", "{x}"].iter() {
+        cache.get(fmtstr).unwrap();
+    }
+    // evicts "{x}{y}", then "{y}" which was used before "{x}"
+    cache.get("a").unwrap();
+    cache.get("b").unwrap();
+    let misses = cache.misses();
+    for fmtstr in ["{x}", "a", "b"].iter() {
+        cache.get(fmtstr).unwrap();
+    }
+    assert_eq!(cache.misses(), misses);
+    cache.get("{y}").unwrap();
+    cache.get("{x}{y}").unwrap();
+    assert_eq!(cache.misses(), misses + 2);
+    assert_eq!(cache.len(), 3);
+}
+
+#[test]
+fn test_cache_errors() {
+    let cache = TemplateCache::new(2);
+    let vars = vars();
+    assert_eq!(
+        cache.strfmt("{x", &vars),
+        Err(FmtError::Invalid(
+            "Expected '}' before end of string".to_string()
+        ))
+    );
+    assert!(cache.is_empty());
+    assert_eq!(
+        cache.strfmt("{z}", &vars),
+        Err(FmtError::KeyError("Invalid key: z".to_string()))
+    );
+    assert_eq!(cache.len(), 1);
+
+    let cache = TemplateCache::new(0);
+    assert_eq!(cache.strfmt("{x}", &vars).unwrap(), "1");
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_cache_threads() {
+    let cache = Arc::new(TemplateCache::new(4));
+    let vars = Arc::new(vars());
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let cache = cache.clone();
+            let vars = vars.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    assert_eq!(cache.strfmt("{x}+{y}", &*vars).unwrap(), "1+2");
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(cache.hits() + cache.misses(), 400);
+    assert_eq!(cache.len(), 1);
+}
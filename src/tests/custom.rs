@@ -0,0 +1,83 @@
+//! Test types that use their own format spec.
+
+use super::super::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+struct Date {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl DisplayStr for Date {
+    fn display_str(&self, f: &mut Formatter) -> Result<()> {
+        if !f.raw_spec().contains('%') {
+            let s = format!("{:04}-{:02}-{:02}", self.year, self.month, self.day);
+            return f.str(&s);
+        }
+        let mut out = String::new();
+        let mut chars = f.raw_spec().chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => write!(out, "{:04}", self.year).unwrap(),
+                Some('m') => write!(out, "{:02}", self.month).unwrap(),
+                Some('d') => write!(out, "{:02}", self.day).unwrap(),
+                Some(c) => {
+                    return Err(FmtError::Invalid(format!("unknown date code: %{}", c)));
+                }
+                None => return Err(FmtError::Invalid("trailing %".to_string())),
+            }
+        }
+        f.write_str(&out).unwrap();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_raw_spec() {
+    let mut s = String::new();
+    let f = Formatter::from_str("x:<.3", &mut s).unwrap();
+    assert_eq!(f.raw_spec(), "<.3");
+    let f = Formatter::from_str("x", &mut s).unwrap();
+    assert_eq!(f.raw_spec(), "");
+    let f = Formatter::from_str("x:", &mut s).unwrap();
+    assert_eq!(f.raw_spec(), "");
+}
+
+#[test]
+fn test_custom_spec() {
+    let mut vars: HashMap<String, Date> = HashMap::new();
+    vars.insert(
+        "when".to_string(),
+        Date {
+            year: 2016,
+            month: 3,
+            day: 7,
+        },
+    );
+
+    assert_eq!(strfmt("{when:%Y-%m-%d}", &vars).unwrap(), "2016-03-07");
+    assert_eq!(strfmt("on {when:%d/%m}!", &vars).unwrap(), "on 07/03!");
+    // the standard spec still works
+    assert_eq!(strfmt("{when:>12}", &vars).unwrap(), "  2016-03-07");
+    assert!(strfmt("{when:%Q}", &vars).is_err());
+}
+
+#[test]
+fn test_builtin_rejects_custom_spec() {
+    let mut vars: HashMap<String, String> = HashMap::new();
+    vars.insert("x".to_string(), "X".to_string());
+    assert!(matches!(
+        strfmt("{x:%Y}", &vars),
+        Err(FmtError::Invalid(_))
+    ));
+
+    // skip leaves any spec untouched
+    let f = |fmt: Formatter| fmt.skip();
+    assert_eq!(strfmt_map("{x:%Y-%m}", f).unwrap(), "{x:%Y-%m}");
+}
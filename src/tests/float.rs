@@ -32,3 +32,131 @@ fn test_fmt_float32() -> Result<(), FmtError> {
 
     Ok(())
 }
+
+#[test]
+fn test_fmt_general() -> Result<(), FmtError> {
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("Third".to_string(), 1.0 / 3.0);
+    vars.insert("Big".to_string(), 1234567.0);
+    vars.insert("Small".to_string(), 0.00001234);
+    vars.insert("Round".to_string(), 2.5);
+    vars.insert("Inf".to_string(), f64::INFINITY);
+
+    assert_eq!("0.333333", "{Third:g}".format(&vars)?);
+    assert_eq!("0.33", "{Third:.2g}".format(&vars)?);
+    assert_eq!("1.23457e6", "{Big:g}".format(&vars)?);
+    assert_eq!("1234567", "{Big:.7g}".format(&vars)?);
+    assert_eq!("1.234E-5", "{Small:G}".format(&vars)?);
+    assert_eq!("2.5", "{Round:g}".format(&vars)?);
+    assert_eq!("2", "{Round:.0g}".format(&vars)?);
+    assert_eq!("   2.5", "{Round:>6g}".format(&vars)?);
+    assert_eq!("inf", "{Inf:g}".format(&vars)?);
+
+    let mut vars: HashMap<String, f32> = HashMap::new();
+    vars.insert("Third".to_string(), 1.0 / 3.0);
+    assert_eq!("0.3333", "{Third:.4g}".format(&vars)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_fmt_grouping() -> Result<(), FmtError> {
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("Big".to_string(), 1234567.891);
+    vars.insert("Neg".to_string(), -1234.5);
+    vars.insert("Part".to_string(), 0.25);
+
+    assert_eq!("1,234,567.891", "{Big:,}".format(&vars)?);
+    assert_eq!("1_234_567.89", "{Big:_.2f}".format(&vars)?);
+    assert_eq!("-1,234.50", "{Neg:,.2f}".format(&vars)?);
+    assert_eq!("  -1,234", "{Neg:>8,.0f}".format(&vars)?);
+    assert_eq!("25.000000%", "{Part:%}".format(&vars)?);
+    assert_eq!("25.0%", "{Part:.1%}".format(&vars)?);
+    assert_eq!("123,456,789.1%", "{Big:,.1%}".format(&vars)?);
+
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("Big".to_string(), 1234567);
+    vars.insert("Neg".to_string(), -1000);
+    vars.insert("Byte".to_string(), 0xdeadbeef);
+
+    assert_eq!("1,234,567", "{Big:,}".format(&vars)?);
+    assert_eq!("-1_000", "{Neg:_}".format(&vars)?);
+    assert_eq!("dead_beef", "{Byte:_x}".format(&vars)?);
+    // integers have no float types without python mode
+    assert!("{Big:.2f}".format(&vars).is_err());
+    assert!("{Neg:.0%}".format(&vars).is_err());
+    assert!("{Byte:,x}".format(&vars).is_err());
+    assert_eq!("0xdead_beef", "{Byte:#_x}".format(&vars)?);
+    assert_eq!("+1,234,567", "{Big:+,}".format(&vars)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_fmt_sign_padding() -> Result<(), FmtError> {
+    // the `+` sign and base prefix are written before the padding
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("x".to_string(), 42);
+    vars.insert("big".to_string(), 3000);
+    vars.insert("neg".to_string(), -42);
+    assert_eq!("+   42", "{x:>+5}".format(&vars)?);
+    assert_eq!("+42   ", "{x:<+5}".format(&vars)?);
+    assert_eq!("+    3000", "{big:+8}".format(&vars)?);
+    assert_eq!("0x    2a", "{x:#6x}".format(&vars)?);
+    assert_eq!("0x                      BB8", "{big:#25X}".format(&vars)?);
+    // a minus sign is part of the number
+    assert_eq!("  -42", "{neg:5}".format(&vars)?);
+    // an explicit alignment pads with zeros before the number
+    assert_eq!("42", "{x:>0}".format(&vars)?);
+    assert_eq!("00000002A", "{x:>09X}".format(&vars)?);
+    assert_eq!("00042", "{x:0>5}".format(&vars)?);
+
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("x".to_string(), 1.5);
+    vars.insert("z".to_string(), -0.0);
+    assert_eq!("+  1.5", "{x:+5}".format(&vars)?);
+    assert_eq!("+  1.5", "{x:+5g}".format(&vars)?);
+    assert_eq!("-0", "{z:+}".format(&vars)?);
+    // debug is a type of the rust syntax only
+    assert!("{x:?}".format(&vars).is_err());
+
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("x".to_string(), 42);
+    assert!("{x:?}".format(&vars).is_err());
+    let mut vars: HashMap<String, &str> = HashMap::new();
+    vars.insert("x".to_string(), "a");
+    assert!("{x:?}".format(&vars).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_fmt_bit_width() -> Result<(), FmtError> {
+    // negative numbers in a base are two's complement of their type,
+    // or of the bit width after the type
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("neg".to_string(), -31);
+    vars.insert("pos".to_string(), 200);
+    assert_eq!("ffffffffffffffe1", "{neg:x}".format(&vars)?);
+    assert_eq!("0xffffffe1", "{neg:#010x/32}".format(&vars)?);
+    assert_eq!("0x0000ffe1", "{neg:#010x/16}".format(&vars)?);
+    assert_eq!("11100001", "{neg:b/8}".format(&vars)?);
+    assert_eq!("FFE1", "{neg:X/16}".format(&vars)?);
+    assert_eq!("c8", "{pos:x/8}".format(&vars)?);
+    assert_eq!(
+        "ffffffffffffffffffffffffffffffe1",
+        "{neg:x/128}".format(&vars)?
+    );
+
+    // the value must fit in the bits
+    assert!("{neg:x/5}".format(&vars).is_err());
+    assert!("{pos:x/7}".format(&vars).is_err());
+    // only for the bases, without a sign
+    assert!("{neg:/32}".format(&vars).is_err());
+    assert!("{neg:+x/32}".format(&vars).is_err());
+    assert!("{neg:x/0}".format(&vars).is_err());
+    assert!("{neg:x/129}".format(&vars).is_err());
+    assert!("{neg:x/}".format(&vars).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars() -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("body".to_string(), "one\ntwo\n\nthree".to_string());
+    vars.insert("x".to_string(), "a\nb".to_string());
+    vars
+}
+
+#[test]
+fn test_indent_annotation() {
+    let vars = vars();
+    assert_eq!(
+        strfmt("key: {body!indent}", &vars).unwrap(),
+        "key: one\n     two\n\n     three"
+    );
+    assert_eq!(
+        strfmt("  - key: {body!indent=leading}", &vars).unwrap(),
+        "  - key: one\n  two\n\n  three"
+    );
+    assert_eq!(
+        strfmt("\tx = {x!indent=leading}", &vars).unwrap(),
+        "\tx = a\n\tb"
+    );
+    // only the literal text of the line counts
+    assert_eq!(
+        strfmt("first\n{x}: {x!indent}", &vars).unwrap(),
+        "first\na\nb: a\n  b"
+    );
+    assert_eq!(
+        strfmt("> {x!indent=column:>4}", &vars).unwrap(),
+        ">  a\n  b"
+    );
+    assert_eq!(strfmt("  {x!indent=off}", &vars).unwrap(), "  a\nb");
+    assert_eq!(strfmt("  {x}", &vars).unwrap(), "  a\nb");
+    // a value ending with a newline is not followed by an indent
+    let mut vars = vars;
+    vars.insert("x".to_string(), "a\n".to_string());
+    assert_eq!(strfmt("  {x!indent}|", &vars).unwrap(), "  a\n|");
+}
+
+#[test]
+fn test_indent_errors() {
+    let vars = vars();
+    // a key with an unknown annotation is looked up whole
+    assert_eq!(
+        strfmt("{x!bogus}", &vars),
+        Err(FmtError::KeyError("Invalid key: x!bogus".to_string()))
+    );
+    assert_eq!(
+        strfmt("{x!indent=2}", &vars),
+        Err(FmtError::Invalid("Invalid indent: 2".to_string()))
+    );
+    let mut bang = HashMap::new();
+    bang.insert("a!b".to_string(), "ok".to_string());
+    assert_eq!(strfmt("{a!b:>3}", &bang).unwrap(), " ok");
+    assert_eq!(Template::parse("{a!b}").unwrap().keys(), vec!["a!b"]);
+    assert!(strfmt("{!indent}", &vars).is_err());
+}
+
+#[test]
+fn test_indent_options() {
+    let vars = vars();
+    let options = Options::new().with_indent(Indent::Column);
+    assert_eq!(
+        strfmt_with("  {x} {x}", &vars, options).unwrap(),
+        "  a\n  b a\n   b"
+    );
+    assert_eq!(
+        strfmt_with("  {x!indent=off}", &vars, options).unwrap(),
+        "  a\nb"
+    );
+    let options = Options::new().with_indent(Indent::Leading);
+    assert_eq!(
+        strfmt_with("    - {x}", &vars, options).unwrap(),
+        "    - a\n    b"
+    );
+}
+
+#[test]
+fn test_indent_template() {
+    let vars = vars();
+    let template = Template::parse("items:\n  - {body!indent}\n  - {x!indent=leading}").unwrap();
+    assert_eq!(template.keys(), vec!["body", "x"]);
+    assert_eq!(
+        template.render(&vars).unwrap(),
+        "items:\n  - one\n    two\n\n    three\n  - a\n  b"
+    );
+    let template = Template::parse("> {x}").unwrap();
+    let options = Options::new().with_indent(Indent::Column);
+    assert_eq!(template.render_with(&vars, options).unwrap(), "> a\n  b");
+}
+
+#[test]
+fn test_indent_padding() {
+    let vars = vars();
+    // the indent is not counted in the width
+    assert_eq!(strfmt("| {x!indent:<4}|", &vars).unwrap(), "| a\n  b |");
+}
@@ -74,3 +74,16 @@ fn test_key_enum() {
         Err(FmtError::KeyError("Invalid key: you".into()))
     );
 }
+
+#[test]
+fn test_key_btreemap() {
+    let mut vars: BTreeMap<String, f64> = BTreeMap::new();
+    vars.insert("x".to_string(), 1.5);
+
+    assert_eq!(strfmt("{x:>5}|", &vars).unwrap(), "  1.5|");
+    assert_eq!("{x:.2}".format(&vars).unwrap(), "1.50");
+    assert_eq!(
+        strfmt("{y}", &vars),
+        Err(FmtError::KeyError("Invalid key: y".into()))
+    );
+}
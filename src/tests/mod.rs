@@ -1,10 +1,34 @@
+mod ansi;
+mod cache;
+mod custom;
 mod float;
+#[allow(clippy::bool_assert_comparison)]
 mod fmt;
+mod indent;
 mod key;
 mod legacy;
+#[allow(unused_imports)]
 mod macros;
+mod python;
+mod registry;
+mod spec;
+#[allow(
+    clippy::redundant_pattern,
+    clippy::nonminimal_bool,
+    clippy::assertions_on_constants
+)]
 mod strfmt;
+mod style;
+mod syntax;
+mod template;
 mod test_trait;
+mod translate;
+mod truncate;
+#[cfg(feature = "unicode")]
+mod unicode;
+mod value;
+mod wrap;
+mod write;
 
 use super::FmtError;
 
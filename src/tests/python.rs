@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use {strfmt_with, FmtError, Options};
+
+fn python<T: ::DisplayStr>(fmtstr: &str, x: T) -> Result<String, FmtError> {
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), x);
+    strfmt_with(fmtstr, &vars, Options::new().with_python(true))
+}
+
+#[test]
+fn test_python_float() -> Result<(), FmtError> {
+    // expectations from python 3.11
+    let values = [
+        ("{x}", 1e16, "1e+16"),
+        ("{x}", 1e15, "1000000000000000.0"),
+        ("{x}", 1e-5, "1e-05"),
+        ("{x}", -0.0, "-0.0"),
+        ("{x}", 12345.678, "12345.678"),
+        ("{x:.2}", 12.0, "1.2e+01"),
+        ("{x:.3}", 1.0, "1.0"),
+        ("{x:.2}", 0.0, "0.0"),
+        ("{x:e}", 100.0, "1.000000e+02"),
+        ("{x:.0e}", 100.0, "1e+02"),
+        ("{x:E}", 5e-324, "4.940656E-324"),
+        ("{x:g}", 1e-5, "1e-05"),
+        ("{x:g}", 0.0, "0"),
+        ("{x:#g}", 1.0, "1.00000"),
+        ("{x:#.0f}", 1.0, "1."),
+        ("{x:f}", 1.5, "1.500000"),
+        ("{x:%}", 0.25, "25.000000%"),
+        ("{x:F}", f64::INFINITY, "INF"),
+        ("{x:f}", f64::NAN, "nan"),
+        ("{x:010}", f64::INFINITY, "0000000inf"),
+        ("{x:_}", 12345.678, "12_345.678"),
+        ("{x:010,.2f}", f64::INFINITY, "0000000inf"),
+        ("{x:010,}", f64::NAN, "0000000nan"),
+        ("{x:+010_}", f64::NEG_INFINITY, "-000000inf"),
+        ("{x:*=10,}", f64::NEG_INFINITY, "-******inf"),
+        ("{x:010,.1%}", f64::INFINITY, "000000inf%"),
+        ("{x:010,.1F}", f64::NAN, "0000000NAN"),
+        ("{x: }", 1.5, " 1.5"),
+        ("{x:+010.2f}", 42.4242, "+000042.42"),
+        ("{x:*=10.2f}", -42.4242, "-****42.42"),
+    ];
+    for &(fmtstr, x, expected) in values.iter() {
+        assert_eq!(python(fmtstr, x)?, expected, "{}", fmtstr);
+    }
+    assert_eq!(python("{x}", 0.1f32)?, "0.1");
+    Ok(())
+}
+
+#[test]
+fn test_python_int() -> Result<(), FmtError> {
+    assert_eq!(python("{x:08,}", 1234)?, "0,001,234");
+    assert_eq!(python("{x:07,}", 1234)?, "001,234");
+    assert_eq!(python("{x:010,}", 1234)?, "00,001,234");
+    assert_eq!(python("{x:*=8}", -42)?, "-*****42");
+    assert_eq!(python("{x:+010}", 42)?, "+000000042");
+    assert_eq!(python("{x:#010x}", 255)?, "0x000000ff");
+    assert_eq!(python("{x: }", 42)?, " 42");
+    assert_eq!(python("{x:e}", 1)?, "1.000000e+00");
+    assert_eq!(python("{x:f}", 1)?, "1.000000");
+    assert_eq!(python("{x:d}", 42)?, "42");
+    assert_eq!(python("{x: d}", 42)?, " 42");
+    assert_eq!(python("{x:,d}", 1234567)?, "1,234,567");
+    assert_eq!(python("{x:+08d}", -42)?, "-0000042");
+    assert_eq!(python("{x:n}", 1234)?, "1234");
+    assert_eq!(python("{x:n}", 1.5)?, "1.5");
+    assert_eq!(python("{x:n}", 1e-5)?, "1e-05");
+    assert_eq!(python("{x:n}", 1234567.0)?, "1.23457e+06");
+    assert!(python("{x:d}", 1.5).is_err());
+    assert!(python("{x:d}", "a").is_err());
+    assert!(python("{x:,n}", 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_python_long_numbers() -> Result<(), FmtError> {
+    // longer than the buffer numbers are formatted in on the stack
+    let zeros = format!("0{},001", ",000".repeat(49));
+    assert_eq!(python("{x:0200,}", 1)?, zeros);
+    let digits = format!("2.5{}", "0".repeat(149));
+    assert_eq!(python("{x:,.150f}", 2.5)?, digits);
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), 2.5);
+    assert_eq!(strfmt_with("{x:,.150f}", &vars, Options::new())?, digits);
+    assert_eq!(strfmt_with("{x:.150g}", &vars, Options::new())?, "2.5");
+    Ok(())
+}
+
+#[test]
+fn test_python_char() -> Result<(), FmtError> {
+    // the `c` type writes the char of an integer's code point
+    assert_eq!(python("{x:c}", 65)?, "A");
+    assert_eq!(python("{x:c}", 0xe9)?, "\u{e9}");
+    assert_eq!(python("{x:*>3c}", 65)?, "**A");
+    assert_eq!(python("{x:<3c}|", 65)?, "A  |");
+    for &(fmtstr, x) in [
+        ("{x:+c}", 65),
+        ("{x:#c}", 65),
+        ("{x:c}", -1),
+        ("{x:c}", 0x110000),
+    ]
+    .iter()
+    {
+        match python(fmtstr, x) {
+            Err(FmtError::Invalid(_)) => {}
+            r => panic!("{}: {:?}", fmtstr, r),
+        }
+    }
+
+    // without python mode, integers have no `c` or float types
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), 65);
+    assert!(strfmt_with("{x:c}", &vars, Options::new()).is_err());
+    assert!(strfmt_with("{x:f}", &vars, Options::new()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_positive_zero() -> Result<(), FmtError> {
+    assert_eq!(python("{x:z.2f}", -0.0001)?, "0.00");
+    assert_eq!(python("{x:z}", -0.0)?, "0.0");
+    assert_eq!(python("{x:z.2f}", -0.01)?, "-0.01");
+    assert_eq!(python("{x:z.2f}", -0.0001f32)?, "0.00");
+
+    // `z` also works without python mode
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), -0.0001);
+    assert_eq!(strfmt_with("{x:z.2}", &vars, Options::new())?, "0.00");
+    assert_eq!(strfmt_with("{x:.2}", &vars, Options::new())?, "-0.00");
+
+    match python("{x:z}", 1) {
+        Err(FmtError::Invalid(_)) => {}
+        r => panic!("{:?}", r),
+    }
+    match python("{x:zx}", 1) {
+        Err(FmtError::Invalid(_)) => {}
+        r => panic!("{:?}", r),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_python_negative_base() -> Result<(), FmtError> {
+    // python writes the sign and magnitude
+    assert_eq!(python("{x:#x}", -31)?, "-0x1f");
+    assert_eq!(python("{x:#010x}", -31)?, "-0x000001f");
+    assert_eq!(python("{x:08b}", -5)?, "-0000101");
+    assert_eq!(python("{x:o}", -8)?, "-10");
+    assert_eq!(python("{x:#_b}", -5)?, "-0b101");
+    assert_eq!(python("{x:X}", -255)?, "-FF");
+    assert_eq!(python("{x:x}", i64::MIN)?, "-8000000000000000");
+    // unless a bit width asks for two's complement
+    assert_eq!(python("{x:#010x/32}", -31)?, "0xffffffe1");
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use super::super::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.add_spec("money", ">12,.2f").unwrap();
+    registry.add_spec("ratio", ">8.1%").unwrap();
+    registry.add_custom("dollars", |f, v| {
+        f.write_str("$").unwrap();
+        v.display_str(f)
+    });
+    registry
+}
+
+#[test]
+fn test_registry() {
+    let registry = registry();
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("price".to_string(), 1234.5);
+    vars.insert("ratio".to_string(), 0.25);
+
+    assert!(registry.contains("money"));
+    assert!(!registry.contains("price"));
+    assert_eq!(
+        strfmt_registry("{price:@money}|", &vars, &registry).unwrap(),
+        "    1,234.50|"
+    );
+    assert_eq!(
+        strfmt_registry("{ratio:@ratio}|", &vars, &registry).unwrap(),
+        "   25.0%|"
+    );
+    assert_eq!(
+        strfmt_registry("{price:@dollars}", &vars, &registry).unwrap(),
+        "$1234.5"
+    );
+    // normal specs are unaffected
+    assert_eq!(
+        strfmt_registry("{price:.1}", &vars, &registry).unwrap(),
+        "1234.5"
+    );
+}
+
+#[test]
+fn test_registry_errors() {
+    let mut registry = registry();
+    assert!(registry.add_spec("bad", "<<<").is_err());
+    assert!(!registry.contains("bad"));
+
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("price".to_string(), 1234.5);
+    assert_eq!(
+        strfmt_registry("{price:@nope}", &vars, &registry),
+        Err(FmtError::KeyError("Invalid preset: nope".to_string()))
+    );
+    assert_eq!(
+        strfmt_registry("{cost:@money}", &vars, &registry),
+        Err(FmtError::KeyError("Invalid key: cost".to_string()))
+    );
+}
+
+#[test]
+fn test_registry_map() {
+    let registry = registry();
+    let f = |fmt: Formatter| registry.display_str(fmt, &42.0);
+    assert_eq!(strfmt_map("{x:@money}", f).unwrap(), "       42.00");
+    // skip keeps the preset reference
+    let f = |fmt: Formatter| fmt.skip();
+    assert_eq!(strfmt_map("{x:@money}", f).unwrap(), "{x:@money}");
+}
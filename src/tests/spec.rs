@@ -0,0 +1,176 @@
+use super::super::*;
+use std::fmt;
+
+#[test]
+fn test_spec_parse() {
+    let spec: FormatSpec = "".parse().unwrap();
+    assert_eq!(spec, FormatSpec::new());
+    assert_eq!(spec.fill(), ' ');
+    assert_eq!(spec.align(), Alignment::Unspecified);
+
+    let spec: FormatSpec = "x<+#10,.3e".parse().unwrap();
+    assert_eq!(spec.fill(), 'x');
+    assert_eq!(spec.align(), Alignment::Left);
+    assert_eq!(spec.sign(), Sign::Plus);
+    assert!(spec.alternate());
+    assert!(!spec.zero());
+    assert_eq!(spec.width(), Some(10));
+    assert_eq!(spec.grouping(), Some(','));
+    assert_eq!(spec.precision(), Some(3));
+    assert_eq!(spec.ty(), Some('e'));
+
+    // the zero flag
+    let spec: FormatSpec = "08".parse().unwrap();
+    assert!(spec.zero());
+    assert_eq!(spec.fill(), '0');
+    assert_eq!(spec.align(), Alignment::Equal);
+    assert_eq!(spec.width(), Some(8));
+    let spec: FormatSpec = "<08".parse().unwrap();
+    assert_eq!(spec.fill(), '0');
+    assert_eq!(spec.align(), Alignment::Left);
+
+    assert!("<<<".parse::<FormatSpec>().is_err());
+    assert!("a34".parse::<FormatSpec>().is_err());
+    assert!(".".parse::<FormatSpec>().is_err());
+    assert!(",s".parse::<FormatSpec>().is_err());
+    assert!("_x".parse::<FormatSpec>().is_ok());
+    assert!("+z.2f".parse::<FormatSpec>().unwrap().positive_zero());
+    assert!("zx".parse::<FormatSpec>().is_err());
+}
+
+#[test]
+fn test_spec_display() {
+    let canonical = vec![
+        "",
+        "<",
+        "*^10",
+        " >5",
+        "+",
+        " ",
+        "#x",
+        "08",
+        "<08",
+        "+#010,.2e",
+        "_b",
+        "z.2f",
+        "#010x/32",
+        "+z",
+        ".3",
+        "ಠ^7s",
+    ];
+    for s in canonical {
+        let spec: FormatSpec = s.parse().unwrap();
+        assert_eq!(spec.to_string(), s);
+        assert_eq!(spec.to_string().parse::<FormatSpec>().unwrap(), spec);
+    }
+}
+
+#[test]
+fn test_spec_builder() {
+    let spec = FormatSpec::builder()
+        .fill('0')
+        .align(Alignment::Right)
+        .width(5)
+        .build()
+        .unwrap();
+    assert_eq!(spec.to_string(), "0>5");
+    assert_eq!(spec, "0>5".parse().unwrap());
+
+    let spec = FormatSpec::builder().zero(true).width(3).build().unwrap();
+    assert_eq!(spec, "03".parse().unwrap());
+
+    assert!(FormatSpec::builder().ty('q').build().is_err());
+    assert!(FormatSpec::builder().grouping(',').ty('x').build().is_err());
+    assert!(FormatSpec::builder().grouping('.').build().is_err());
+    assert_eq!(
+        FormatSpec::builder().fill('*').width(5).build(),
+        Err(FmtError::Invalid("Fill requires an alignment".to_string()))
+    );
+}
+
+#[test]
+fn test_spec_builder_round_trip() {
+    let specs = vec![
+        FormatSpec::builder().fill('*').align(Alignment::Left).zero(true),
+        FormatSpec::builder().zero(true).align(Alignment::Right).width(9),
+        FormatSpec::builder().zero(true).sign(Sign::Plus).width(6).precision(2),
+        FormatSpec::builder().fill('0').align(Alignment::Equal).grouping(','),
+        FormatSpec::builder().alternate(true).width(10).ty('x').bits(16),
+        FormatSpec::builder().precision(3).ellipsis(Cut::Middle, "...").ty('s'),
+    ];
+    for builder in specs {
+        let spec = builder.build().unwrap();
+        assert_eq!(spec.to_string().parse::<FormatSpec>(), Ok(spec.clone()));
+    }
+    let spec = FormatSpec::builder()
+        .fill('*')
+        .align(Alignment::Left)
+        .zero(true)
+        .build()
+        .unwrap();
+    assert!(!spec.zero());
+}
+
+#[test]
+fn test_formatter_new() {
+    let spec: FormatSpec = "*^7".parse().unwrap();
+    let mut out = String::new();
+    {
+        let mut f = Formatter::new("x", spec.clone(), &mut out);
+        assert_eq!(f.spec(), spec);
+        assert_eq!(f.raw_spec(), "");
+        f.str("abc").unwrap();
+    }
+    {
+        let mut f = Formatter::new("x", spec.clone(), &mut out);
+        f.i64(42).unwrap();
+    }
+    Formatter::new("y", spec, &mut out).skip().unwrap();
+    Formatter::new("z", FormatSpec::new(), &mut out)
+        .skip()
+        .unwrap();
+    assert_eq!(out, "**abc****42***{y:*^7}{z}");
+}
+
+/// a sink that can only hold a few bytes
+struct Small(String);
+
+impl fmt::Write for Small {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.0.len() + s.len() > 4 {
+            return Err(fmt::Error);
+        }
+        self.0.push_str(s);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_formatter_sink_error() {
+    let mut out = Small(String::new());
+    let spec: FormatSpec = ">3".parse().unwrap();
+    Formatter::new("x", spec.clone(), &mut out).str("a").unwrap();
+    assert_eq!(out.0, "  a");
+    assert!(matches!(
+        Formatter::new("x", spec, &mut out).str("b"),
+        Err(FmtError::WriteError(_))
+    ));
+}
+
+#[test]
+#[cfg(not(feature = "unicode"))]
+fn test_width_mode_without_unicode() {
+    let mut vars = std::collections::HashMap::new();
+    vars.insert("s".to_string(), "ab");
+    let columns = Options::new().with_width_mode(WidthMode::Columns);
+    assert!(matches!(
+        strfmt_with("{s:>4}", &vars, columns),
+        Err(FmtError::Invalid(_))
+    ));
+    assert!(matches!(
+        strfmt("{s!width=graphemes:>4}", &vars),
+        Err(FmtError::Invalid(_))
+    ));
+    let chars = Options::new().with_width_mode(WidthMode::Chars);
+    assert_eq!(strfmt_with("{s:>4}", &vars, chars).unwrap(), "  ab");
+}
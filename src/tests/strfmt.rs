@@ -142,6 +142,10 @@ fn test_values() {
     ];
 
     run_tests(&values, &vars, &strfmt);
+    let render = |fmtstr: &str, vars: &HashMap<String, String>| -> Result<String> {
+        Template::parse(fmtstr)?.render(vars)
+    };
+    run_tests(&values, &vars, &render);
 }
 
 #[test]
@@ -0,0 +1,78 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars() -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("status".to_string(), "ok".to_string());
+    vars.insert("n".to_string(), "3".to_string());
+    vars
+}
+
+#[test]
+fn test_style() {
+    let vars = vars();
+    assert_eq!(
+        strfmt("[{status!style=red,bold}]", &vars).unwrap(),
+        "[\x1b[1;31mok\x1b[0m]"
+    );
+    // the padding is styled too, and only the value counts in the width
+    assert_eq!(
+        strfmt("[{status!style=on_blue:^6}]", &vars).unwrap(),
+        "[\x1b[44m  ok  \x1b[0m]"
+    );
+    assert_eq!(
+        strfmt("{status!style=green!indent:>4}", &vars).unwrap(),
+        "\x1b[32m  ok\x1b[0m"
+    );
+    let mut ints = HashMap::new();
+    ints.insert("n".to_string(), 42);
+    assert_eq!(
+        strfmt("{n!style=bright_yellow:*>3}", &ints).unwrap(),
+        "\x1b[93m*42\x1b[0m"
+    );
+}
+
+#[test]
+fn test_style_strip() {
+    let vars = vars();
+    let options = Options::new().with_styles(false);
+    assert!(Options::new().styles());
+    assert!(!options.styles());
+    assert_eq!(
+        strfmt_with("[{status!style=red:>4}]", &vars, options).unwrap(),
+        "[  ok]"
+    );
+    let template = Template::parse("{n}: {status!style=underline}").unwrap();
+    assert_eq!(template.render(&vars).unwrap(), "3: \x1b[4mok\x1b[0m");
+    assert_eq!(template.render_with(&vars, options).unwrap(), "3: ok");
+}
+
+#[test]
+fn test_style_no_output() {
+    // a skipped field or one with no output is not styled
+    let skip = |fmt: Formatter| fmt.skip();
+    assert_eq!(
+        strfmt_map("[{status!style=red:>4}]", skip).unwrap(),
+        "[{status!style=red:>4}]"
+    );
+    let empty = |mut fmt: Formatter| fmt.str("");
+    assert_eq!(strfmt_map("[{status!style=red}]", empty).unwrap(), "[]");
+    let template = Template::parse("[{status!style=red}]").unwrap();
+    assert_eq!(template.render_map(skip).unwrap(), "[{status!style=red}]");
+    assert_eq!(template.render_map(empty).unwrap(), "[]");
+}
+
+#[test]
+fn test_style_errors() {
+    let vars = vars();
+    assert_eq!(
+        strfmt("{status!style=purple}", &vars),
+        Err(FmtError::Invalid("Invalid style: purple".to_string()))
+    );
+    assert_eq!(
+        strfmt("{status!style}", &vars),
+        Err(FmtError::Invalid("Invalid style: ".to_string()))
+    );
+    assert!(Template::parse("{status!style=red,,bold}").is_err());
+    assert!(strfmt("{nope!style=red}", &vars).is_err());
+}
@@ -0,0 +1,342 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars() -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("name".to_string(), "bob".to_string());
+    vars.insert("color".to_string(), "red".to_string());
+    vars
+}
+
+fn render(syntax: Syntax, fmtstr: &str) -> Result<String> {
+    strfmt_with(fmtstr, &vars(), Options::new().with_syntax(syntax))
+}
+
+#[test]
+fn test_dollar() {
+    assert_eq!(
+        render(Syntax::Dollar, "{\"name\": \"${name}\"} costs $$5 or $6").unwrap(),
+        "{\"name\": \"bob\"} costs $5 or $6"
+    );
+    assert_eq!(
+        render(Syntax::Dollar, "a { color: ${color:>5}; }").unwrap(),
+        "a { color:   red; }"
+    );
+    assert_eq!(
+        render(Syntax::Dollar, "${name!style=bold}").unwrap(),
+        "\x1b[1mbob\x1b[0m"
+    );
+    assert_eq!(
+        render(Syntax::Dollar, "${nope}"),
+        Err(FmtError::KeyError("Invalid key: nope".to_string()))
+    );
+    assert!(render(Syntax::Dollar, "${name").is_err());
+    assert!(render(Syntax::Dollar, "${:>5}").is_err());
+}
+
+#[test]
+fn test_mustache() {
+    assert_eq!(
+        render(Syntax::Mustache, "\\textbf{ {{name}} } {{ color:^7 }}").unwrap(),
+        "\\textbf{ bob }   red  "
+    );
+    assert_eq!(
+        render(Syntax::Mustache, "\\{{name}} {{name}}").unwrap(),
+        "{{name}} bob"
+    );
+    assert!(render(Syntax::Mustache, "{{name}").is_err());
+}
+
+#[test]
+fn test_percent() {
+    assert_eq!(
+        render(Syntax::Percent, "%(name)s is %(color)5s, 100%%").unwrap(),
+        "bob is   red, 100%"
+    );
+    assert_eq!(
+        render(Syntax::Percent, "[%(name)-5s] [%(name).2s]").unwrap(),
+        "[bob  ] [bo]"
+    );
+    let mut nums = HashMap::new();
+    nums.insert("x".to_string(), 255);
+    let options = Options::new().with_syntax(Syntax::Percent);
+    assert_eq!(
+        strfmt_with("%(x)d %(x)+d %(x)#x %(x)X %(x)6o", &nums, options).unwrap(),
+        "255 +255 0xff FF    377"
+    );
+    let mut floats = HashMap::new();
+    floats.insert("f".to_string(), 1.23456);
+    assert_eq!(
+        strfmt_with("%(f).2f|%(f)8.3f|%(f)-8.1e|", &floats, options).unwrap(),
+        "1.23|   1.235|1.2e0   |"
+    );
+    assert_eq!(
+        render(Syntax::Percent, "%(name)n"),
+        Err(FmtError::TypeError(
+            "Unknown conversion type \"n\"".to_string()
+        ))
+    );
+    assert!(render(Syntax::Percent, "%(name)g").is_err());
+    assert!(render(Syntax::Percent, "50%").is_err());
+}
+
+#[test]
+fn test_printf() {
+    let options = Options::new().with_syntax(Syntax::Percent);
+    let args: Vec<Box<dyn DisplayStr>> = vec![
+        Box::new("disk"),
+        Box::new(93.456),
+        Box::new(48879),
+        Box::new(48879),
+        Box::new(65),
+        Box::new(65),
+    ];
+    assert_eq!(
+        strfmt_with("%-6s|%5.1f%%|%#x %X %lu|%3c", &args, options).unwrap(),
+        "disk  | 93.5%|0xbeef BEEF 65|  A"
+    );
+    assert_eq!(
+        strfmt_with("%s %s", &["a", "b"][..], options).unwrap(),
+        "a b"
+    );
+    assert_eq!(
+        strfmt_with("%.3g|%G|%e", &[0.000123456, 1e20, 1.5][..], options).unwrap(),
+        "0.000123|1E20|1.500000e0"
+    );
+    assert_eq!(
+        strfmt_with("%-5s|%6.2f", &[1.5, 2.25][..], options).unwrap(),
+        "1.5  |  2.25"
+    );
+    // like C, `%f` and `%e` default to 6 digits and format integers as floats
+    assert_eq!(
+        strfmt_with("%f|%e|%.1f|%.*f", &[42, 15, 2, -1, 1][..], options).unwrap(),
+        "42.000000|1.500000e1|2.0|1.000000"
+    );
+    // the `0` flag pads numbers with zeros after the sign, like python
+    assert_eq!(
+        strfmt_with(
+            "%08x|%05d|%+05d|%-05d|%#08x",
+            &[255, -42, 42, 5, 255][..],
+            options
+        )
+        .unwrap(),
+        "000000ff|-0042|+0042|5    |0x0000ff"
+    );
+    let args: Vec<Box<dyn DisplayStr>> = vec![
+        Box::new("disk"),
+        Box::new(93.456),
+        Box::new(48879),
+        Box::new(-1.5),
+        Box::new("ab"),
+    ];
+    assert_eq!(
+        strfmt_with("%-10s %5.2f %08x|%08.2f|%05s", &args, options).unwrap(),
+        "disk       93.46 0000beef|-0001.50|   ab"
+    );
+    assert_eq!(
+        strfmt_with("%s %s", &["a"][..], options),
+        Err(FmtError::KeyError("Invalid key: 1".to_string()))
+    );
+}
+
+#[test]
+fn test_printf_star() {
+    let options = Options::new().with_syntax(Syntax::Percent);
+    let args: Vec<Box<dyn DisplayStr>> = vec![
+        Box::new(6),
+        Box::new("ab"),
+        Box::new(-6),
+        Box::new("cd"),
+        Box::new(8),
+        Box::new(2),
+        Box::new(1.23456),
+        Box::new(-1),
+        Box::new("ef"),
+    ];
+    assert_eq!(
+        strfmt_with("[%*s][%*s][%*.*f][%.*s]", &args, options).unwrap(),
+        "[    ab][cd    ][    1.23][ef]"
+    );
+    let args = ["x", "y"];
+    assert_eq!(
+        strfmt_with("%*s", &args[..], options),
+        Err(FmtError::TypeError(
+            "* wants an integer, got \"x\" for key 0".to_string()
+        ))
+    );
+    let template = Template::parse_with("%(name)s %*d %s", Syntax::Percent).unwrap();
+    assert_eq!(template.keys(), vec!["name", "0", "1", "2"]);
+}
+
+#[test]
+fn test_syntax_template() {
+    let vars = vars();
+    let template = Template::parse_with("${name} {name} ${color:.1}", Syntax::Dollar).unwrap();
+    assert_eq!(template.keys(), vec!["name", "color"]);
+    assert_eq!(template.render(&vars).unwrap(), "bob {name} r");
+    // the syntax of a template is not changed by the options
+    let options = Options::new().with_syntax(Syntax::Braces);
+    assert_eq!(
+        template.render_with(&vars, options).unwrap(),
+        "bob {name} r"
+    );
+    let template = Template::parse_with("{{name}}: %(x)s", Syntax::Mustache).unwrap();
+    assert_eq!(template.render(&vars).unwrap(), "bob: %(x)s");
+}
+
+#[test]
+fn test_syntax_skip() {
+    let vars = vars();
+    let skip = |mut fmt: Formatter| match vars.display_var(&mut fmt) {
+        Err(FmtError::KeyError(_)) => fmt.skip(),
+        r => r,
+    };
+    let cases = [
+        (Syntax::Braces, "{name} {other:>3}", "bob {other:>3}"),
+        (Syntax::Dollar, "${name} ${other:>3}", "bob ${other:>3}"),
+        (Syntax::Mustache, "{{name}} {{ other }}", "bob {{other}}"),
+        (
+            Syntax::Percent,
+            "%(name)s %(other)-3d %5s",
+            "bob %(other)-3d %5s",
+        ),
+    ];
+    for &(syntax, fmtstr, expected) in cases.iter() {
+        let template = Template::parse_with(fmtstr, syntax).unwrap();
+        assert_eq!(template.render_map(skip).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_dotnet() {
+    let options = Options::new().with_syntax(Syntax::DotNet);
+    let args: Vec<Box<dyn DisplayStr>> = vec![
+        Box::new("disk"),
+        Box::new(1234.5678),
+        Box::new(0.1234),
+        Box::new(255),
+        Box::new(-42),
+        Box::new(1e20),
+        Box::new(0.00001),
+    ];
+    let render = |fmtstr: &str| strfmt_with(fmtstr, &args, options);
+    assert_eq!(
+        render("[{0,-6}][{0,6}][{0}]").unwrap(),
+        "[disk  ][  disk][disk]"
+    );
+    assert_eq!(
+        render("{1:N2}|{1:N0}|{1:F}|{1:F1}").unwrap(),
+        "1,234.57|1,235|1234.57|1234.6"
+    );
+    assert_eq!(
+        render("{1:C}|{4:C}|{1,12:C}").unwrap(),
+        "$1,234.57|-$42.00|   $1,234.57"
+    );
+    assert_eq!(render("{2:P1}|{2:P}|{3:N0}").unwrap(), "12.3%|12.34%|255");
+    assert_eq!(
+        render("{3:X}|{3:X8}|{3:x4}|{4:D5}|{3:D}").unwrap(),
+        "FF|000000FF|00ff|-00042|255"
+    );
+    assert_eq!(
+        render("{1:E2}|{1:e}|{1:G}|{1:G3}|{1:R}").unwrap(),
+        "1.23E+003|1.234568e+003|1234.5678|1.23E+03|1234.5678"
+    );
+    assert_eq!(
+        render("{5:G}|{5:E}|{6:g}|{6:E1}|{3:E2}|{3:G}|{4:G2}").unwrap(),
+        "1E+20|1.000000E+020|1e-05|1.0E-005|2.55E+002|255|-42"
+    );
+    assert_eq!(
+        render("{1:D}"),
+        Err(FmtError::TypeError(
+            "Unknown format code 'd' for type".to_string()
+        ))
+    );
+    assert_eq!(render("{{{0}}} {1,-10:N1}|").unwrap(), "{disk} 1,234.6   |");
+    assert_eq!(render("{3,-5:X}|{3, 5}|").unwrap(), "FF   |  255|");
+    assert_eq!(
+        render("{1:0.00}"),
+        Err(FmtError::TypeError(
+            "Unsupported format string: \"0.00\"".to_string()
+        ))
+    );
+    assert!(render("{1:N100}").is_err());
+    assert!(render("{1,x}").is_err());
+    assert!(render("{,5}").is_err());
+    assert!(render("{9}").is_err());
+
+    let template = Template::parse_with("{0,-8}{1,10:N2}", Syntax::DotNet).unwrap();
+    assert_eq!(template.keys(), vec!["0", "1"]);
+    assert_eq!(template.render(&args).unwrap(), "disk      1,234.57");
+}
+
+#[test]
+fn test_rust() {
+    let options = Options::new().with_syntax(Syntax::Rust);
+    let mut vars: HashMap<String, Box<dyn DisplayStr>> = HashMap::new();
+    vars.insert("name".to_string(), Box::new("bob"));
+    vars.insert("n".to_string(), Box::new(255i64));
+    vars.insert("x".to_string(), Box::new(1234.5678));
+    vars.insert("neg".to_string(), Box::new(-42i32));
+    vars.insert("w".to_string(), Box::new(8usize));
+    vars.insert("p".to_string(), Box::new(2usize));
+    vars.insert("prec".to_string(), Box::new(2usize));
+    let mut render = |fmtstr: &str, args: Vec<Box<dyn DisplayStr>>| {
+        for (i, arg) in args.into_iter().enumerate() {
+            vars.insert(i.to_string(), arg);
+        }
+        strfmt_with(fmtstr, &vars, options)
+    };
+    // the expected output is from `format!`
+    assert_eq!(
+        render(
+            "[{name:>w$}][{:<3$}][{:.*}][{x:.p$}][{x:+.prec$e}]",
+            vec![Box::new("ab"), Box::new(2), Box::new(1.23456), Box::new(6)]
+        )
+        .unwrap(),
+        "[     bob][ab    ][1.23][1234.57][+1.23e3]"
+    );
+    assert_eq!(
+        render(
+            "[{n:#010b}][{n:#x}][{n:x?}][{n:#X?}][{neg:08}][{neg:+}][{neg:x}][{n:^+9}]",
+            vec![]
+        )
+        .unwrap(),
+        "[0b11111111][0xff][ff][0xFF][-0000042][-42][ffffffd6][  +255   ]"
+    );
+    assert_eq!(
+        render(
+            "[{x:e}][{x:E}][{x:010.1}][{:?}][{:?}][{name:?}][{name:>8?}][{:e}]",
+            vec![Box::new(1.0), Box::new(0.1), Box::new(1500)]
+        )
+        .unwrap(),
+        "[1.2345678e3][1.2345678E3][00001234.6][1.0][0.1][\"bob\"][\"bob\"][1.5e3]"
+    );
+    assert_eq!(
+        render(
+            "[{}][{0}][{}][{name:*^9}][{n:<05}][{x:-^12.3}][{!style=bold}]",
+            vec![Box::new("a"), Box::new("b"), Box::new("c")]
+        )
+        .unwrap(),
+        "[a][a][b][***bob***][00255][--1234.568--][\x1b[1mc\x1b[0m]"
+    );
+    assert_eq!(
+        render(
+            "[{n:.2}][{:.*}][{neg:>6.1}]",
+            vec![Box::new(3), Box::new(1234)]
+        )
+        .unwrap(),
+        "[255][1234][   -42]"
+    );
+    assert_eq!(
+        render("{name:s}", vec![]),
+        Err(FmtError::TypeError(
+            "Unknown format trait \"s\"".to_string()
+        ))
+    );
+    assert!(render("{x:,}", vec![]).is_err());
+    assert!(render("{x:.}", vec![]).is_err());
+    assert!(render("{x:>nope$}", vec![]).is_err());
+
+    let template = Template::parse_with("{name:>w$} {:.*} {}", Syntax::Rust).unwrap();
+    assert_eq!(template.keys(), vec!["w", "name", "0", "1", "2"]);
+}
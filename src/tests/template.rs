@@ -0,0 +1,165 @@
+use super::super::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[test]
+fn test_template() {
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("x".to_string(), 42);
+    vars.insert("y".to_string(), -7);
+
+    let template = Template::parse("{{{x:>4}}} and {y:<3}|").unwrap();
+    assert_eq!(template.keys(), vec!["x", "y"]);
+    assert_eq!(template.render(&vars).unwrap(), "{  42} and -7 |");
+    // rendering again gives the same result
+    assert_eq!(template.render(&vars).unwrap(), "{  42} and -7 |");
+    assert_eq!(
+        template,
+        "{{{x:>4}}} and {y:<3}|".parse::<Template>().unwrap()
+    );
+
+    let f = |fmt: Formatter| fmt.skip();
+    assert_eq!(template.render_map(f).unwrap(), "{{x:>4}} and {y:<3}|");
+
+    assert!(Template::parse("{x").is_err());
+    assert!(Template::parse("{:3}").is_err());
+    // spec errors are found when rendering
+    let template = Template::parse("{x:<<<}").unwrap();
+    assert!(matches!(
+        template.render(&vars),
+        Err(FmtError::TypeError(_))
+    ));
+    let template = Template::parse("{z}").unwrap();
+    assert_eq!(
+        template.render(&vars),
+        Err(FmtError::KeyError("Invalid key: z".to_string()))
+    );
+}
+
+#[test]
+fn test_render_into_slice() {
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("x".to_string(), 2.25);
+    let template = Template::parse("ಠ_ಠ {x:^8.2}!").unwrap();
+
+    let mut buf = [0u8; 32];
+    let len = render_into_slice(&template, &vars, &mut buf).unwrap();
+    assert_eq!(std::str::from_utf8(&buf[..len]).unwrap(), "ಠ_ಠ   2.25  !");
+
+    // exactly the right size
+    let mut exact = vec![0u8; len];
+    assert_eq!(render_into_slice(&template, &vars, &mut exact), Ok(len));
+
+    let mut small = vec![0u8; len - 1];
+    assert!(matches!(
+        render_into_slice(&template, &vars, &mut small),
+        Err(FmtError::BufferOverflow(_))
+    ));
+    // other errors are still reported
+    let template = Template::parse("{y}").unwrap();
+    assert!(matches!(
+        render_into_slice(&template, &vars, &mut buf),
+        Err(FmtError::KeyError(_))
+    ));
+}
+
+#[test]
+fn test_long_numbers() {
+    // too long for the stack buffer used to format numbers
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("big".to_string(), 1e300);
+    let expected = format!("{}", 1e300);
+    assert_eq!(expected.len(), 301);
+    assert_eq!(strfmt("{big}", &vars).unwrap(), expected);
+
+    let mut padded = " ".repeat(10);
+    padded.push_str(&format!("{:.1}", 1e300));
+    assert_eq!(strfmt("{big:>313.1}", &vars).unwrap(), padded);
+
+    let mut centered = "*".to_string();
+    centered.push_str(&expected);
+    centered.push_str("**");
+    assert_eq!(strfmt("{big:*^304}", &vars).unwrap(), centered);
+}
+
+#[test]
+fn test_cow() {
+    let mut vars: HashMap<String, String> = HashMap::new();
+    vars.insert("x".to_string(), "X".to_string());
+
+    let borrowed = |r: Result<Cow<str>>| matches!(r, Ok(Cow::Borrowed(_)));
+    assert!(borrowed(strfmt_cow("no fields", &vars)));
+    assert!(borrowed(strfmt_cow("", &vars)));
+    assert!(!borrowed(strfmt_cow("{{x}}", &vars)));
+    assert_eq!(strfmt_cow("{{x}}", &vars).unwrap(), "{x}");
+    assert_eq!(strfmt_cow("{x}!", &vars).unwrap(), "X!");
+    assert!(strfmt_cow("x}", &vars).is_err());
+
+    let template = Template::parse("{{x}} and }}").unwrap();
+    assert!(template.is_static());
+    assert!(borrowed(template.render_cow(&vars)));
+    assert_eq!(template.render_cow(&vars).unwrap(), "{x} and }");
+    assert!(Template::parse("").unwrap().is_static());
+    assert!(borrowed(Template::parse("").unwrap().render_cow(&vars)));
+
+    let template = Template::parse("{x}").unwrap();
+    assert!(!template.is_static());
+    assert!(!borrowed(template.render_cow(&vars)));
+    assert_eq!(template.render_cow(&vars).unwrap(), "X");
+}
+
+#[test]
+fn test_render_each() {
+    let rows: Vec<HashMap<String, f64>> = [1.0, 22.5, 333.25]
+        .iter()
+        .map(|&v| {
+            let mut row = HashMap::new();
+            row.insert("v".to_string(), v);
+            row
+        })
+        .collect();
+    let template = Template::parse("[{v:>7.2}]").unwrap();
+
+    let each = template.render_each(&rows);
+    assert_eq!(each.size_hint(), (3, Some(3)));
+    let lines: Vec<String> = each.map(|r| r.unwrap()).collect();
+    assert_eq!(lines, vec!["[   1.00]", "[  22.50]", "[ 333.25]"]);
+
+    // one buffer is reused for every row
+    let mut each = template.render_each(&rows);
+    assert_eq!(each.next_str().unwrap().unwrap(), "[   1.00]");
+    assert_eq!(each.next_str().unwrap().unwrap(), "[  22.50]");
+    assert_eq!(each.next(), Some(Ok("[ 333.25]".to_string())));
+    assert!(each.next_str().is_none());
+
+    let mut out = String::from("> ");
+    assert_eq!(
+        template.render_each_write(&rows, " | ", &mut out).unwrap(),
+        3
+    );
+    assert_eq!(out, "> [   1.00] | [  22.50] | [ 333.25]");
+    out.clear();
+    assert_eq!(
+        template
+            .render_each_write(&rows[..0], ",", &mut out)
+            .unwrap(),
+        0
+    );
+    assert_eq!(out, "");
+
+    // errors are given per row
+    let mut rows = rows;
+    rows[1].clear();
+    let results: Vec<_> = template.render_each(&rows).collect();
+    assert!(results[0].is_ok());
+    assert_eq!(
+        results[1],
+        Err(FmtError::KeyError("Invalid key: v".to_string()))
+    );
+    assert_eq!(results[2], Ok("[ 333.25]".to_string()));
+    let mut each = template.render_each(&rows);
+    assert!(each.next_str().unwrap().is_ok());
+    assert!(each.next_str().unwrap().is_err());
+    assert_eq!(each.next_str().unwrap(), Ok("[ 333.25]"));
+    assert!(template.render_each_write(&rows, "\n", &mut out).is_err());
+}
@@ -0,0 +1,165 @@
+use super::super::*;
+use std::collections::HashMap;
+
+const SYNTAXES: [Syntax; 6] = [
+    Syntax::Braces,
+    Syntax::Dollar,
+    Syntax::Mustache,
+    Syntax::Percent,
+    Syntax::DotNet,
+    Syntax::Rust,
+];
+
+fn translate(fmtstr: &str, from: Syntax, to: Syntax) -> Translation {
+    Template::parse_with(fmtstr, from).unwrap().translate(to)
+}
+
+#[test]
+fn test_translate_printf() {
+    let args: Vec<Box<dyn DisplayStr>> = vec![Box::new("disk"), Box::new(93.456), Box::new(48879)];
+    let fmtstr = "%-6s|%5.1f%%|%#x {}";
+    let template = Template::parse_with(fmtstr, Syntax::Percent).unwrap();
+    let expected = template.render(&args).unwrap();
+    assert_eq!(expected, "disk  | 93.5%|0xbeef {}");
+    let translation = template.translate(Syntax::Braces);
+    assert_eq!(translation.text(), "{0:<6}|{1:>5.1f}%|{2:#x} {{}}");
+    assert!(translation.is_lossless());
+    let braces = Template::parse(translation.text()).unwrap();
+    assert_eq!(braces.render(&args).unwrap(), expected);
+    // and back again
+    let back = braces.translate(Syntax::Percent);
+    assert_eq!(back.text(), "%-6s|%5.1f%%|%#x {}");
+    assert!(back.is_lossless());
+    // braces have no `c` type
+    let translation = translate("%c", Syntax::Percent, Syntax::Braces);
+    assert_eq!(translation.text(), "{0}");
+    assert_eq!(translation.lossy()[0].reason(), "the type");
+}
+
+#[test]
+fn test_translate_all() {
+    let mut vars: HashMap<String, Box<dyn DisplayStr>> = HashMap::new();
+    vars.insert("name".to_string(), Box::new("bob"));
+    vars.insert("0".to_string(), Box::new(1234.5678));
+    vars.insert("n".to_string(), Box::new(-42));
+    let fmtstr = "{{$%\\ {{{{ {name:<6}|{0:>12,.2f}|{n:+}|{n:>5}}}";
+    let template = Template::parse(fmtstr).unwrap();
+    let expected = template.render(&vars).unwrap();
+    assert_eq!(expected, "{$%\\ {{ bob   |    1,234.57|-42|  -42}");
+    for &syntax in SYNTAXES.iter() {
+        let translation = template.translate(syntax);
+        let text = translation.text();
+        let lossy: Vec<_> = translation.lossy().iter().map(|l| l.field()).collect();
+        match syntax {
+            Syntax::Percent => assert_eq!(lossy, vec!["{0:>12,.2f}"]),
+            Syntax::DotNet => assert_eq!(lossy, vec!["{n:+}"]),
+            Syntax::Rust => assert_eq!(lossy, vec!["{0:>12,.2f}"]),
+            _ => {
+                assert!(translation.is_lossless(), "{:?}", translation);
+                let rendered = Template::parse_with(text, syntax).unwrap().render(&vars);
+                assert_eq!(rendered.unwrap(), expected, "{}", text);
+            }
+        }
+    }
+    assert_eq!(
+        translate(fmtstr, Syntax::Braces, Syntax::Dollar).text(),
+        "{$$%\\ {{ ${name:<6}|${0:>12,.2f}|${n:+}|${n:>5}}"
+    );
+    assert_eq!(
+        translate(fmtstr, Syntax::Braces, Syntax::Mustache).text(),
+        "{$%\\ \\{{ {{name:<6}}|{{0:>12,.2f}}|{{n:+}}|{{n:>5}}}"
+    );
+}
+
+#[test]
+fn test_translate_lossy() {
+    let lossy = |fmtstr: &str, from: Syntax, to: Syntax| -> Vec<(String, &'static str)> {
+        translate(fmtstr, from, to)
+            .lossy()
+            .iter()
+            .map(|l| (l.field().to_string(), l.reason()))
+            .collect()
+    };
+    assert_eq!(
+        lossy(
+            "{a:^10} {b:*<5} {c:10} {d:.3…} {e:,} {f:b}",
+            Syntax::Braces,
+            Syntax::Percent
+        ),
+        vec![
+            ("{a:^10}".to_string(), "the alignment"),
+            ("{b:*<5}".to_string(), "the fill"),
+            ("{c:10}".to_string(), "the alignment"),
+            ("{d:.3…}".to_string(), "the ellipsis"),
+            ("{e:,}".to_string(), "the grouping"),
+            ("{f:b}".to_string(), "the type"),
+        ]
+    );
+    assert_eq!(
+        lossy("%*d %.*f %s", Syntax::Percent, Syntax::Braces),
+        vec![
+            ("%*d".to_string(), "the width or precision argument"),
+            ("%.*f".to_string(), "the width or precision argument"),
+        ]
+    );
+    assert_eq!(
+        lossy("{0:C} {0:D5} {0:N1} {0:X4}", Syntax::DotNet, Syntax::Braces),
+        vec![
+            ("{0:C}".to_string(), "the number format"),
+            ("{0:D5}".to_string(), "the number format"),
+            ("{0:X4}".to_string(), "the number format"),
+        ]
+    );
+    assert_eq!(
+        lossy("{x:#x/32} {y:x/8}", Syntax::Braces, Syntax::Rust),
+        vec![
+            ("{x:#x/32}".to_string(), "the bit width"),
+            ("{y:x/8}".to_string(), "the bit width"),
+        ]
+    );
+    assert_eq!(
+        translate("{x:#010x/32}", Syntax::Braces, Syntax::Mustache).text(),
+        "{{x:#010x/32}}"
+    );
+    assert_eq!(
+        lossy("\\{x} {x:zs}", Syntax::Braces, Syntax::Mustache),
+        vec![
+            ("{x}".to_string(), "the text before the field"),
+            ("{x:zs}".to_string(), "the invalid spec"),
+        ]
+    );
+    assert_eq!(
+        lossy("{x!style=red,bold}", Syntax::Braces, Syntax::DotNet),
+        vec![("{x!style=red,bold}".to_string(), "the annotations")]
+    );
+}
+
+#[test]
+fn test_translate_keys() {
+    // only fields with the next positional key are positional conversions
+    let translation = translate("{1} {0} {1!indent} {x}", Syntax::Braces, Syntax::Percent);
+    assert_eq!(translation.text(), "%(1)s %s %(1!indent)s %(x)s");
+    assert!(translation.is_lossless());
+    let translation = translate(
+        "{0,-8}{1,10:N2} {1:P1} {1:C0} {2:x8}",
+        Syntax::DotNet,
+        Syntax::DotNet,
+    );
+    assert_eq!(translation.text(), "{0,-8}{1,10:N2} {1:P1} {1:C0} {2:x8}");
+    assert!(translation.is_lossless());
+    let translation = translate("${a!style=red,bold:>4}", Syntax::Dollar, Syntax::Braces);
+    assert_eq!(translation.into_text(), "{a!style=bold,red:>4}");
+
+    // rust fields without a key are numbered
+    let translation = translate("{} {:08.2} {name:>w$} {:x?}", Syntax::Rust, Syntax::Braces);
+    assert_eq!(translation.text(), "{0} {1:08.2} {name} {2:x}");
+    let lossy: Vec<_> = translation.lossy().iter().map(|l| l.reason()).collect();
+    assert_eq!(
+        lossy,
+        vec!["zero padding", "the width or precision argument"]
+    );
+    let translation = translate("%-5s|%08.2f|%#x", Syntax::Percent, Syntax::Rust);
+    assert_eq!(translation.text(), "{0:<5}|{1:>08.2}|{2:#x}");
+    assert_eq!(translation.lossy()[0].field(), "%08.2f");
+    assert_eq!(translation.lossy()[0].reason(), "the type");
+}
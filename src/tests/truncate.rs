@@ -0,0 +1,116 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars() -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("s".to_string(), "hello world".to_string());
+    vars.insert("short".to_string(), "hi".to_string());
+    vars
+}
+
+#[test]
+fn test_ellipsis() {
+    let vars = vars();
+    let f = |fmtstr: &str| strfmt(fmtstr, &vars).unwrap();
+    assert_eq!(f("{s:.5}"), "hello");
+    assert_eq!(f("{s:.5\u{2026}}"), "hell\u{2026}");
+    assert_eq!(f("{s:.5>\u{2026}}"), "hell\u{2026}");
+    assert_eq!(f("{s:.5<\u{2026}}"), "\u{2026}orld");
+    assert_eq!(f("{s:.5^\u{2026}}"), "he\u{2026}ld");
+    assert_eq!(f("{s:.8...}"), "hello...");
+    assert_eq!(f("{s:.8<...}"), "...world");
+    assert_eq!(f("{s:.6^...}"), "he...d");
+    assert_eq!(f("{s:.6^...s}"), "he...d");
+    // padding counts the marker
+    assert_eq!(f("[{s:>8.5\u{2026}}]"), "[   hell\u{2026}]");
+    assert_eq!(f("[{s:*^9.5<\u{2026}}]"), "[**\u{2026}orld**]");
+    // strings that fit are not changed
+    assert_eq!(f("{s:.11\u{2026}}"), "hello world");
+    assert_eq!(f("[{short:<4.3...}]"), "[hi  ]");
+    assert_eq!(f("{s:.3...}"), "...");
+    // a precision smaller than the marker can't fit it
+    for fmtstr in ["{s:.2...}", "{s:.1...}", "{s:.0^...}"].iter() {
+        assert_eq!(
+            strfmt(fmtstr, &vars),
+            Err(FmtError::Invalid(
+                "Precision shorter than the truncation marker".to_string()
+            ))
+        );
+    }
+}
+
+#[test]
+fn test_ellipsis_ansi() {
+    let mut vars = HashMap::new();
+    vars.insert("s".to_string(), "\x1b[31mhello world".to_string());
+    let options = Options::new().with_ansi(true);
+    assert_eq!(
+        strfmt_with("{s:.5\u{2026}}", &vars, options).unwrap(),
+        "\x1b[31mhell\x1b[0m\u{2026}"
+    );
+
+    // escapes are kept whole and don't count, the end keeps its style
+    vars.insert("s".to_string(), "\x1b[31mhello\x1b[0m world".to_string());
+    let f = |fmtstr: &str| strfmt_with(fmtstr, &vars, options).unwrap();
+    assert_eq!(
+        f("{s:.8^\u{2026}}"),
+        "\x1b[31mhell\x1b[0m\u{2026}\x1b[0mrld"
+    );
+    assert_eq!(f("{s:.4^\u{2026}}"), "\x1b[31mhe\x1b[0m\u{2026}\x1b[0md");
+    assert_eq!(f("{s:.8<\u{2026}}"), "\u{2026}\x1b[31mo\x1b[0m world");
+    assert_eq!(
+        f("[{s:>9.5<\u{2026}}]"),
+        "[    \u{2026}\x1b[31m\x1b[0morld]"
+    );
+    vars.insert(
+        "s".to_string(),
+        "plain \x1b[32mgreen text\x1b[0m".to_string(),
+    );
+    let f = |fmtstr: &str| strfmt_with(fmtstr, &vars, options).unwrap();
+    assert_eq!(f("{s:.8<\u{2026}}"), "\u{2026}\x1b[32men text\x1b[0m");
+    assert_eq!(f("{s:.8^\u{2026}}"), "plai\u{2026}\x1b[32mext\x1b[0m");
+}
+
+#[test]
+fn test_ellipsis_spec() {
+    let spec: FormatSpec = "<10.5^...".parse().unwrap();
+    assert_eq!(spec.ellipsis(), Some((Cut::Middle, "...")));
+    assert_eq!(spec.to_string(), "<10.5^...");
+    let spec: FormatSpec = ".5>\u{2026}".parse().unwrap();
+    assert_eq!(spec.ellipsis(), Some((Cut::End, "\u{2026}")));
+    assert_eq!(spec.to_string(), ".5\u{2026}");
+
+    let built = FormatSpec::builder()
+        .precision(4)
+        .ellipsis(Cut::Start, "~")
+        .build()
+        .unwrap();
+    let mut out = String::new();
+    Formatter::new("s", built, &mut out).str("abcdef").unwrap();
+    assert_eq!(out, "~def");
+
+    assert_eq!(
+        FormatSpec::builder().ellipsis(Cut::End, "...").build(),
+        Err(FmtError::Invalid(
+            "Truncation marker requires a precision".to_string()
+        ))
+    );
+    assert_eq!(
+        ".3...e".parse::<FormatSpec>(),
+        Err(FmtError::TypeError(
+            "Truncation marker not allowed with type 'e'".to_string()
+        ))
+    );
+    // a cut without a marker is still an invalid type
+    assert!(".3>".parse::<FormatSpec>().is_err());
+    assert!(".3..".parse::<FormatSpec>().is_err());
+
+    let mut vars: HashMap<String, f64> = HashMap::new();
+    vars.insert("x".to_string(), 1.5);
+    assert_eq!(
+        strfmt("{x:.2\u{2026}}", &vars),
+        Err(FmtError::TypeError(
+            "Truncation marker not allowed for floats".to_string()
+        ))
+    );
+}
@@ -0,0 +1,135 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn vars(s: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("s".to_string(), s.to_string());
+    vars
+}
+
+fn columns() -> Options {
+    Options::new().with_width_mode(WidthMode::Columns)
+}
+
+#[test]
+fn test_columns() {
+    let wide = vars("日本");
+    // chars are counted by default
+    assert_eq!(strfmt("[{s:>6}]", &wide).unwrap(), "[    日本]");
+    assert_eq!(
+        strfmt_with("[{s:>6}]", &wide, columns()).unwrap(),
+        "[  日本]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:^7}]", &wide, columns()).unwrap(),
+        "[ 日本  ]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:<6}]", &vars("👍!"), columns()).unwrap(),
+        "[👍!   ]"
+    );
+
+    // truncating never keeps half of a wide char
+    assert_eq!(strfmt_with("[{s:.3}]", &wide, columns()).unwrap(), "[日]");
+    assert_eq!(
+        strfmt_with("[{s:<4.3}]", &wide, columns()).unwrap(),
+        "[日  ]"
+    );
+    assert_eq!(strfmt_with("[{s:.4}]", &wide, columns()).unwrap(), "[日本]");
+
+    let template = Template::parse("[{s:>6}]").unwrap();
+    assert_eq!(template.render_with(&wide, columns()).unwrap(), "[  日本]");
+}
+
+#[test]
+fn test_graphemes() {
+    // "e" followed by a combining acute accent
+    let accent = vars("cafe\u{301}s");
+    assert_eq!(strfmt("[{s:>7}]", &accent).unwrap(), "[ cafe\u{301}s]");
+    let options = Options::new().with_width_mode(WidthMode::Graphemes);
+    assert_eq!(
+        strfmt_with("[{s:>7}]", &accent, options).unwrap(),
+        "[  cafe\u{301}s]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:>7}]", &accent, columns()).unwrap(),
+        "[  cafe\u{301}s]"
+    );
+
+    // truncating never splits a grapheme
+    assert_eq!(strfmt("[{s:.4}]", &accent).unwrap(), "[cafe]");
+    assert_eq!(
+        strfmt_with("[{s:.4}]", &accent, options).unwrap(),
+        "[cafe\u{301}]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:.4}]", &accent, columns()).unwrap(),
+        "[cafe\u{301}]"
+    );
+}
+
+#[test]
+fn test_width_mode_spec() {
+    let spec = FormatSpec::builder()
+        .align(Alignment::Right)
+        .width(6)
+        .width_mode(WidthMode::Columns)
+        .build()
+        .unwrap();
+    assert_eq!(spec.width_mode(), Some(WidthMode::Columns));
+    let mut out = String::new();
+    Formatter::new("s", spec.clone(), &mut out)
+        .str("日本")
+        .unwrap();
+    assert_eq!(out, "  日本");
+
+    // the spec overrides the mode of the render
+    let chars = Options::new().with_width_mode(WidthMode::Chars);
+    let mut out = String::new();
+    let mut fmt = Formatter::new("s", spec, &mut out);
+    fmt.set_options(chars);
+    assert_eq!(fmt.width_mode(), WidthMode::Columns);
+    fmt.str("日本").unwrap();
+    assert_eq!(out, "  日本");
+}
+
+#[test]
+fn test_width_mode_annotation() {
+    let wide = vars("日本");
+    assert_eq!(
+        strfmt("[{s!width=columns:>6}|{s:>6}]", &wide).unwrap(),
+        "[  日本|    日本]"
+    );
+    assert_eq!(
+        strfmt_with("[{s!width=chars:>6}]", &wide, columns()).unwrap(),
+        "[    日本]"
+    );
+    let accent = vars("cafe\u{301}s");
+    assert_eq!(
+        strfmt("[{s!width=graphemes:.4}]", &accent).unwrap(),
+        "[cafe\u{301}]"
+    );
+    let template = Template::parse("{s!width=columns:>6}").unwrap();
+    assert_eq!(template.render(&wide).unwrap(), "  日本");
+    assert_eq!(
+        strfmt("{s!width=bytes}", &wide),
+        Err(FmtError::Invalid("Invalid width mode: bytes".to_string()))
+    );
+}
+
+#[test]
+fn test_columns_ellipsis() {
+    let wide = vars("日本語テキスト");
+    assert_eq!(
+        strfmt_with("[{s:.5\u{2026}}]", &wide, columns()).unwrap(),
+        "[日本\u{2026}]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:.6<\u{2026}}]", &wide, columns()).unwrap(),
+        "[\u{2026}スト]"
+    );
+    assert_eq!(
+        strfmt_with("[{s:.7^\u{2026}}]", &wide, columns()).unwrap(),
+        "[日\u{2026}スト]"
+    );
+}
@@ -0,0 +1,33 @@
+use super::super::*;
+
+#[test]
+fn test_format_value() {
+    assert_eq!(format_value("", &42).unwrap(), "42");
+    assert_eq!(format_value("", &"X").unwrap(), "X");
+    assert_eq!(format_value(">10.3e", &1234.5).unwrap(), "   1.234e3");
+    assert_eq!(format_value("<6x", &255u8).unwrap(), "ff    ");
+    assert_eq!(format_value("*^7", &"ಠ_ಠ").unwrap(), "**ಠ_ಠ**");
+    assert_eq!(format_value(".2", &"long").unwrap(), "lo");
+
+    let boxed: Box<dyn DisplayStr> = Box::new(-3i32);
+    assert_eq!(format_value("+", &boxed).unwrap(), "-3");
+
+    assert!(matches!(
+        format_value("<<<", &"x"),
+        Err(FmtError::TypeError(_))
+    ));
+    assert!(matches!(
+        format_value(".2", &42),
+        Err(FmtError::TypeError(_))
+    ));
+}
+
+#[test]
+fn test_format_value_write() {
+    let mut out = "cells:".to_string();
+    for v in [1.5, 22.25, 333.126].iter() {
+        out.push('|');
+        format_value_write(">7.2", v, &mut out).unwrap();
+    }
+    assert_eq!(out, "cells:|   1.50|  22.25| 333.13");
+}
@@ -0,0 +1,117 @@
+use super::super::*;
+use std::collections::HashMap;
+
+fn wrap(fmtstr: &str, s: &str) -> Result<String> {
+    let mut vars = HashMap::new();
+    vars.insert("s".to_string(), s.to_string());
+    strfmt(fmtstr, &vars)
+}
+
+const TEXT: &str = "the quick brown fox jumps over the lazy dog";
+
+#[test]
+fn test_wrap() {
+    assert_eq!(
+        wrap("{s:10~}", TEXT).unwrap(),
+        "the quick \nbrown fox \njumps over\nthe lazy  \ndog       "
+    );
+    assert_eq!(
+        wrap("{s:>10~}", TEXT).unwrap(),
+        " the quick\n brown fox\njumps over\n  the lazy\n       dog"
+    );
+    assert_eq!(
+        wrap("{s:*^11~}", TEXT).unwrap(),
+        "*the quick*\n*brown fox*\njumps over*\n*the lazy**\n****dog****"
+    );
+    // a value that fits is only padded
+    assert_eq!(wrap("[{s:8~}]", "short").unwrap(), "[short   ]");
+    assert_eq!(wrap("[{s:8~}]", "").unwrap(), "[        ]");
+}
+
+#[test]
+fn test_wrap_indent() {
+    assert_eq!(
+        wrap("{s:<12~2}", TEXT).unwrap(),
+        "the quick   \n  brown fox \n  jumps over\n  the lazy  \n  dog       "
+    );
+    assert_eq!(
+        wrap("{s:>12~4}", TEXT).unwrap(),
+        "   the quick\n       brown\n         fox\n       jumps\n    over the\n    lazy dog"
+    );
+    // an indent as wide as the line is ignored
+    assert_eq!(wrap("{s:3~5}", "ab cd").unwrap(), "ab \ncd ");
+}
+
+#[test]
+fn test_wrap_breaks() {
+    // newlines in the value are kept, and spaces between words on a line
+    assert_eq!(
+        wrap("{s:8~}", "one  two\n\nthree").unwrap(),
+        "one  two\n        \nthree   "
+    );
+    // long words are split
+    assert_eq!(
+        wrap("{s:4~}", "abcdefghij kl").unwrap(),
+        "abcd\nefgh\nij  \nkl  "
+    );
+    assert_eq!(wrap("{s:4~1}", "abcdefghij").unwrap(), "abcd\n efg\n hij");
+}
+
+#[test]
+fn test_wrap_ansi() {
+    let mut vars = HashMap::new();
+    vars.insert(
+        "s".to_string(),
+        "\x1b[31mthe quick\x1b[0m brown fox".to_string(),
+    );
+    let options = Options::new().with_ansi(true);
+    // escape sequences take no room on their line
+    assert_eq!(
+        strfmt_with("{s:<12~2}", &vars, options).unwrap(),
+        "\x1b[31mthe quick\x1b[0m   \n  brown fox "
+    );
+    assert_eq!(
+        strfmt_with("{s:>5~}", &vars, options).unwrap(),
+        "  \x1b[31mthe\nquick\x1b[0m\nbrown\n  fox"
+    );
+}
+
+#[test]
+fn test_wrap_spec() {
+    let spec: FormatSpec = "<30~4".parse().unwrap();
+    assert_eq!(spec.wrap(), Some(4));
+    assert_eq!(spec.to_string(), "<30~4");
+    let spec: FormatSpec = "*^30s~".parse().unwrap();
+    assert_eq!(spec.wrap(), Some(0));
+    assert_eq!(spec.to_string(), "*^30s~");
+    let built = FormatSpec::builder().width(10).wrap(2).build().unwrap();
+    assert_eq!(built, "10~2".parse().unwrap());
+
+    assert_eq!(
+        "~".parse::<FormatSpec>(),
+        Err(FmtError::Invalid("Wrapping requires a width".to_string()))
+    );
+    assert_eq!(
+        "10.2~".parse::<FormatSpec>(),
+        Err(FmtError::Invalid(
+            "Precision not allowed with wrapping".to_string()
+        ))
+    );
+    assert_eq!(
+        "10x~".parse::<FormatSpec>(),
+        Err(FmtError::TypeError(
+            "Wrapping not allowed with type 'x'".to_string()
+        ))
+    );
+    assert!("10~x".parse::<FormatSpec>().is_err());
+    assert!("10~2~".parse::<FormatSpec>().is_err());
+
+    let mut vars: HashMap<String, i64> = HashMap::new();
+    vars.insert("x".to_string(), 1);
+    assert_eq!(
+        strfmt("{x:10~}", &vars),
+        Err(FmtError::TypeError(
+            "Wrapping not allowed for integers".to_string()
+        ))
+    );
+}
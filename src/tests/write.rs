@@ -0,0 +1,86 @@
+use super::super::*;
+use std::collections::HashMap;
+use std::io;
+
+fn vars() -> HashMap<String, String> {
+    let mut vars: HashMap<String, String> = HashMap::new();
+    vars.insert("x".to_string(), "X".to_string());
+    vars.insert("name".to_string(), "bob".to_string());
+    vars
+}
+
+#[test]
+fn test_strfmt_write() {
+    let vars = vars();
+    let mut out = String::new();
+    strfmt_write("{x:>3}|", &vars, &mut out).unwrap();
+    strfmt_write("{{{name}}}", &vars, &mut out).unwrap();
+    assert_eq!(out, "  X|{bob}");
+
+    // partial output is kept on error
+    assert!(strfmt_write("a{y}", &vars, &mut out).is_err());
+    assert_eq!(out, "  X|{bob}a");
+}
+
+#[test]
+fn test_strfmt_append() {
+    let vars = vars();
+    let mut out = "hi ".to_string();
+    strfmt_append("{name}", &vars, &mut out).unwrap();
+    assert_eq!(out, "hi bob");
+    assert_eq!(
+        strfmt_append(" and {y}", &vars, &mut out),
+        Err(FmtError::KeyError("Invalid key: y".to_string()))
+    );
+    assert_eq!(out, "hi bob");
+    assert!(strfmt_append(" }", &vars, &mut out).is_err());
+    assert_eq!(out, "hi bob");
+}
+
+#[test]
+fn test_strfmt_map_write() {
+    let mut out = String::new();
+    let f = |mut fmt: Formatter| fmt.i64(42);
+    strfmt_map_write("[{a:^6}]", f, &mut out).unwrap();
+    assert_eq!(out, "[  42  ]");
+}
+
+#[test]
+fn test_strfmt_io() {
+    let vars = vars();
+    let mut out: Vec<u8> = Vec::new();
+    strfmt_io("ಠ_ಠ {name:*<5}\n", &vars, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "ಠ_ಠ bob**\n");
+}
+
+/// a writer that accepts a few bytes, then fails
+struct Broken(usize);
+
+impl io::Write for Broken {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0 < buf.len() {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe is gone"));
+        }
+        self.0 -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_strfmt_io_error() {
+    let vars = vars();
+    assert!(strfmt_io("{name}", &vars, &mut Broken(3)).is_ok());
+    assert_eq!(
+        strfmt_io("{name:10}", &vars, &mut Broken(3)),
+        Err(FmtError::WriteError("pipe is gone".to_string()))
+    );
+    // format errors are not hidden by the writer
+    assert_eq!(
+        strfmt_io("{y}", &vars, &mut Broken(3)),
+        Err(FmtError::KeyError("Invalid key: y".to_string()))
+    );
+}
@@ -0,0 +1,285 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use indent::Annotations;
+use spec::FormatSpec;
+use syntax::Syntax;
+use types::*;
+
+/// A template written in another [Syntax], created by
+/// [Template::translate](crate::Template::translate).
+///
+/// Every field is written as close as the syntax allows. The fields that
+/// would not render the same are listed in [Translation::lossy].
+///
+/// # Examples
+///
+/// ```
+/// use strfmt::{Syntax, Template};
+///
+/// let template = Template::parse_with("%-8s|%5.1f%%|%(x)#x", Syntax::Percent).unwrap();
+/// let translation = template.translate(Syntax::Braces);
+/// assert_eq!(translation.text(), "{0:<8}|{1:>5.1f}%|{x:#x}");
+/// assert!(translation.is_lossless());
+///
+/// let template = Template::parse("{name:^10} {n:+}").unwrap();
+/// let translation = template.translate(Syntax::Percent);
+/// assert_eq!(translation.text(), "%(name)10s %(n)+s");
+/// assert_eq!(translation.lossy()[0].field(), "{name:^10}");
+/// assert_eq!(translation.lossy()[0].reason(), "the alignment");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    pub(crate) text: String,
+    pub(crate) lossy: Vec<LossyField>,
+}
+
+impl Translation {
+    pub(crate) fn new() -> Translation {
+        Translation {
+            text: String::new(),
+            lossy: Vec::new(),
+        }
+    }
+
+    /// the translated format string
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// the translated format string
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// the fields that could not be translated exactly, in order
+    pub fn lossy(&self) -> &[LossyField] {
+        &self.lossy
+    }
+
+    /// return true if every field was translated exactly
+    pub fn is_lossless(&self) -> bool {
+        self.lossy.is_empty()
+    }
+}
+
+/// A field that is not translated exactly, see [Translation::lossy]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LossyField {
+    pub(crate) field: String,
+    pub(crate) reason: &'static str,
+}
+
+impl LossyField {
+    /// the field in the original syntax, i.e. `{name:^10}`
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// the first part of the field that is lost, i.e. `the alignment`
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+}
+
+/// write literal text, escaped for `syntax`
+pub(crate) fn write_literal(syntax: Syntax, s: &str, out: &mut String) {
+    match syntax {
+        Syntax::Braces | Syntax::DotNet | Syntax::Rust => {
+            for c in s.chars() {
+                if c == '{' || c == '}' {
+                    out.push(c);
+                }
+                out.push(c);
+            }
+        }
+        Syntax::Dollar => out.push_str(&s.replace('$', "$$")),
+        Syntax::Mustache => out.push_str(&s.replace("{{", "\\{{")),
+        Syntax::Percent => out.push_str(&s.replace('%', "%%")),
+    }
+}
+
+/// return false if a field written after `text` would be read differently,
+/// like a `\` escaping a [Syntax::Mustache] field
+pub(crate) fn field_can_follow(syntax: Syntax, text: &str) -> bool {
+    match syntax {
+        Syntax::Mustache => !text.ends_with('\\') && !text.ends_with('{'),
+        _ => true,
+    }
+}
+
+/// write a field as close to `spec` as `syntax` allows. `positional` is the
+/// number of positional [Syntax::Percent] fields written so far.
+///
+/// Returns the key the written field has when it is read on its own.
+pub(crate) fn write_field<'k>(
+    syntax: Syntax,
+    key: &'k str,
+    annotations: Annotations,
+    spec: &FormatSpec,
+    positional: &mut usize,
+    out: &mut String,
+) -> &'k str {
+    let (open, close) = syntax.delimiters();
+    out.push_str(open);
+    let key = match syntax {
+        Syntax::Percent => write_percent(key, annotations, spec, positional, out),
+        Syntax::DotNet => {
+            write!(out, "{}{}", key, annotations).unwrap();
+            write_dotnet(spec, out);
+            key
+        }
+        _ => {
+            write!(out, "{}{}", key, annotations).unwrap();
+            let mut spec = normalize(spec);
+            // the `0` flag of rust and `%` pads numbers after the sign, rust
+            // keeps the alignment for strings
+            if spec.number.zero_pad {
+                spec.number.zero_pad = false;
+                spec.zero = true;
+                if syntax != Syntax::Rust {
+                    spec.align = Alignment::Unspecified;
+                }
+            }
+            // rust has no `f` type, a float is written fixed without one
+            if syntax == Syntax::Rust && spec.ty == Some('f') {
+                spec.ty = None;
+            }
+            if syntax == Syntax::Rust {
+                spec.bits = None;
+            } else if spec.ty == Some('?') {
+                // only rust has debug
+                spec.ty = None;
+            }
+            // and only `%` has the char of an integer
+            if spec.ty == Some('c') {
+                spec.ty = None;
+            }
+            if spec != FormatSpec::default() {
+                write!(out, ":{}", spec).unwrap();
+            }
+            key
+        }
+    };
+    out.push_str(close);
+    key
+}
+
+fn write_percent<'k>(
+    key: &'k str,
+    annotations: Annotations,
+    spec: &FormatSpec,
+    positional: &mut usize,
+    out: &mut String,
+) -> &'k str {
+    let read_key = if annotations == Annotations::default() && key.parse() == Ok(*positional) {
+        *positional += 1;
+        "0"
+    } else {
+        write!(out, "({}{})", key, annotations).unwrap();
+        key
+    };
+    if spec.align == Alignment::Left {
+        out.push('-');
+    }
+    match spec.sign {
+        Sign::Plus => out.push('+'),
+        Sign::Space => out.push(' '),
+        Sign::Minus | Sign::Unspecified => {}
+    }
+    if spec.alternate {
+        out.push('#');
+    }
+    let zero = spec.zero && spec.fill.is_none() && spec.align == Alignment::Unspecified;
+    if zero || spec.number.zero_pad && spec.align != Alignment::Left {
+        out.push('0');
+    }
+    if let Some(width) = spec.width {
+        write!(out, "{}", width).unwrap();
+    }
+    if let Some(precision) = spec.precision {
+        write!(out, ".{}", precision).unwrap();
+    }
+    out.push(match spec.ty {
+        Some(c) if "cfeEgGxXo".contains(c) => c,
+        _ => 's',
+    });
+    read_key
+}
+
+fn write_dotnet(spec: &FormatSpec, out: &mut String) {
+    if let Some(width) = spec.width {
+        let sign = if spec.align == Alignment::Left {
+            "-"
+        } else {
+            ""
+        };
+        write!(out, ",{}{}", sign, width).unwrap();
+    }
+    let digits = spec.number.min_digits;
+    let (letter, precision) = match (spec.ty, spec.grouping) {
+        (Some('f'), Some(',')) if spec.number.prefix == "$" => ('C', spec.precision),
+        (Some('f'), Some(',')) => ('N', spec.precision),
+        (Some('f'), None) => ('F', spec.precision),
+        (Some('%'), _) => ('P', spec.precision),
+        (Some(c), _) if "eEgG".contains(c) => (c, spec.precision),
+        (Some(c), _) if c == 'x' || c == 'X' => (c, Some(digits).filter(|&d| d > 0)),
+        (Some('d'), _) => ('D', Some(digits).filter(|&d| d > 0)),
+        (None, _) if digits > 0 => ('D', Some(digits)),
+        (None, _) if spec.number.shortest_exp == Some('E') => ('G', None),
+        (None, _) if spec.number.shortest_exp == Some('e') => ('g', None),
+        _ => return,
+    };
+    out.push(':');
+    out.push(letter);
+    if let Some(precision) = precision {
+        write!(out, "{}", precision).unwrap();
+    }
+}
+
+/// the first part of `from` that is lost when it is read back as `to`
+pub(crate) fn spec_loss(from: &FormatSpec, to: &FormatSpec) -> Option<&'static str> {
+    let (from, to) = (normalize(from), normalize(to));
+    let losses = [
+        (from.align != to.align, "the alignment"),
+        (from.fill != to.fill, "the fill"),
+        (from.zero != to.zero, "zero padding"),
+        (from.sign != to.sign, "the sign"),
+        (
+            from.positive_zero != to.positive_zero,
+            "the negative zero coercion",
+        ),
+        (from.alternate != to.alternate, "the alternate form"),
+        (from.width != to.width, "the width"),
+        (from.grouping != to.grouping, "the grouping"),
+        (from.precision != to.precision, "the precision"),
+        (from.ty != to.ty, "the type"),
+        (from.bits != to.bits, "the bit width"),
+        (from.number != to.number, "the number format"),
+        (from.width_mode != to.width_mode, "the width mode"),
+        (from.ellipsis != to.ellipsis, "the ellipsis"),
+        (from.wrap != to.wrap, "the wrapping"),
+    ];
+    losses
+        .iter()
+        .find(|&&(lost, _)| lost)
+        .map(|&(_, reason)| reason)
+}
+
+/// clear the parts of a spec that don't change how it renders
+fn normalize(spec: &FormatSpec) -> FormatSpec {
+    let mut spec = spec.clone();
+    if spec.width.is_none() {
+        spec.align = Alignment::Unspecified;
+        spec.fill = None;
+        spec.zero = false;
+    }
+    if spec.fill == Some(' ') {
+        spec.fill = None;
+    }
+    if spec.sign == Sign::Minus {
+        spec.sign = Sign::Unspecified;
+    }
+    spec
+}
@@ -1,9 +1,10 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
-use std::result;
-use std::string::String;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Alignment {
     Unspecified, // default Left for strings, Right for numbers
     Left,
@@ -12,7 +13,7 @@ pub enum Alignment {
     Equal,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Sign {
     Unspecified,
     Plus,
@@ -20,7 +21,17 @@ pub enum Sign {
     Space,
 }
 
+/// Where a string longer than the precision is cut when it is truncated
+/// with an ellipsis marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cut {
+    Start,  // `<`: keep the end of the string
+    Middle, // `^`: keep the start and the end
+    End,    // `>`: keep the start of the string, the default
+}
+
 impl Sign {
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_unspecified(&self) -> bool {
         match *self {
             Sign::Unspecified => false,
@@ -32,11 +43,14 @@ impl Sign {
 pub type Result<T> = result::Result<T, FmtError>;
 
 /// LOC-error
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum FmtError {
-    Invalid(String),   // format string is structued incorrectly
-    KeyError(String),  // key error in formatting string
-    TypeError(String), // invalid type used
+    Invalid(String),        // format string is structued incorrectly
+    KeyError(String),       // key error in formatting string
+    TypeError(String),      // invalid type used
+    WriteError(String),     // the output could not be written to
+    BufferOverflow(String), // the output does not fit in the given buffer
 }
 
 impl fmt::Display for FmtError {
@@ -45,16 +59,21 @@ impl fmt::Display for FmtError {
             FmtError::Invalid(ref s) => write!(f, "Invalid({})", s),
             FmtError::KeyError(ref s) => write!(f, "KeyError({})", s),
             FmtError::TypeError(ref s) => write!(f, "TypeError({})", s),
+            FmtError::WriteError(ref s) => write!(f, "WriteError({})", s),
+            FmtError::BufferOverflow(ref s) => write!(f, "BufferOverflow({})", s),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for FmtError {
     fn description(&self) -> &str {
         match *self {
             FmtError::Invalid(_) => "invalid format string",
             FmtError::KeyError(_) => "invalid key",
             FmtError::TypeError(_) => "error during type resolution",
+            FmtError::WriteError(_) => "error writing the output",
+            FmtError::BufferOverflow(_) => "output does not fit in the buffer",
         }
     }
 
@@ -63,6 +82,12 @@ impl error::Error for FmtError {
     }
 }
 
+impl From<fmt::Error> for FmtError {
+    fn from(_: fmt::Error) -> FmtError {
+        FmtError::WriteError("an error occurred when writing the output".to_string())
+    }
+}
+
 // enum Type {
 //     // integer types
 //     Bin,
@@ -0,0 +1,96 @@
+#[cfg(feature = "unicode")]
+use unicode_segmentation::UnicodeSegmentation;
+#[cfg(feature = "unicode")]
+use unicode_width::UnicodeWidthStr;
+
+/// How the width and precision of a string are measured when it is padded
+/// and truncated.
+///
+/// The fill character always counts as one unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WidthMode {
+    /// count `char`s, like python. This is the default.
+    #[default]
+    Chars,
+    /// count grapheme clusters, so combining marks don't add to the width.
+    /// Truncation never splits a grapheme. Formatting with it fails with
+    /// [FmtError::Invalid](crate::FmtError::Invalid) without the `unicode`
+    /// feature.
+    Graphemes,
+    /// count the columns a terminal displays, so wide characters (CJK, emoji)
+    /// count as two. Truncation never splits a grapheme or keeps half of a
+    /// wide character. Like `Graphemes`, it needs the `unicode` feature.
+    Columns,
+}
+
+/// measure `s` with `mode`, stopping at `precision` units. Returns the width
+/// and the number of bytes of `s` that fit.
+pub(crate) fn measure(s: &str, mode: WidthMode, precision: Option<usize>) -> (usize, usize) {
+    match mode {
+        #[cfg(feature = "unicode")]
+        WidthMode::Graphemes => measure_graphemes(s, precision, |_| 1),
+        #[cfg(feature = "unicode")]
+        WidthMode::Columns => measure_graphemes(s, precision, UnicodeWidthStr::width),
+        _ => match precision {
+            Some(p) => match s.char_indices().nth(p) {
+                Some((end, _)) => (p, end),
+                None => (s.chars().count(), s.len()),
+            },
+            None => (s.chars().count(), s.len()),
+        },
+    }
+}
+
+/// measure `s` from the end with `mode`, stopping at `precision` units.
+/// Returns the width and the byte offset of the part of `s` that fits.
+pub(crate) fn measure_back(s: &str, mode: WidthMode, precision: Option<usize>) -> (usize, usize) {
+    let mut width = 0;
+    let mut start = s.len();
+    match mode {
+        #[cfg(feature = "unicode")]
+        WidthMode::Graphemes | WidthMode::Columns => {
+            for (i, grapheme) in s.grapheme_indices(true).rev() {
+                let w = match mode {
+                    WidthMode::Columns => grapheme.width(),
+                    _ => 1,
+                };
+                if let Some(p) = precision {
+                    if width + w > p {
+                        return (width, start);
+                    }
+                }
+                width += w;
+                start = i;
+            }
+        }
+        _ => {
+            for (i, _) in s.char_indices().rev() {
+                if precision == Some(width) {
+                    return (width, start);
+                }
+                width += 1;
+                start = i;
+            }
+        }
+    }
+    (width, start)
+}
+
+/// measure `s` by adding the width of every grapheme
+#[cfg(feature = "unicode")]
+fn measure_graphemes<F>(s: &str, precision: Option<usize>, width_of: F) -> (usize, usize)
+where
+    F: Fn(&str) -> usize,
+{
+    let mut width = 0;
+    for (i, grapheme) in s.grapheme_indices(true) {
+        let w = width_of(grapheme);
+        if let Some(p) = precision {
+            if width + w > p {
+                return (width, i);
+            }
+        }
+        width += w;
+    }
+    (width, s.len())
+}